@@ -1,17 +1,26 @@
 // EBNF:
-// CompUnit      ::= [CompUnit] (Decl | FuncDef);
-// Decl          ::= ConstDecl | VarDecl;
-// ConstDecl     ::= "const" BType ConstDef {"," ConstDef} ";";
-// BType         ::= "int";
-// ConstDef      ::= IDENT "=" ConstInitVal;
-// ConstInitVal  ::= ConstExp;
-// VarDecl       ::= BType VarDef {"," VarDef} ";";
-// VarDef        ::= IDENT | IDENT "=" InitVal;
-// InitVal       ::= Exp;
-
-// FuncDef       ::= BType IDENT "(" [FuncFParams] ")" Block;
+// CompUnit      ::= [CompUnit] (Decl | FuncDecl | FuncDef);
+// Decl          ::= ConstDecl | VarDecl | EnumDecl ";" | StructDecl ";" | TypedefDecl;
+// ConstDecl     ::= ["static"] "const" BType ConstDef {"," ConstDef} ";";
+// BType         ::= "int" | "char" | "enum" IDENT | "struct" IDENT | IDENT;
+// Pointers      ::= {"*"};
+// EnumDecl      ::= "enum" [IDENT] "{" Enumerator {"," Enumerator} [","] "}";
+// Enumerator    ::= IDENT ["=" ConstExp];
+// StructDecl    ::= "struct" IDENT "{" FieldDecl {FieldDecl} "}";
+// FieldDecl     ::= BType StructField {"," StructField} ";";
+// StructField   ::= IDENT {"[" ConstExp "]"};
+// TypedefDecl   ::= "typedef" (BType Pointers | EnumDecl | StructDecl) IDENT ";";
+// ConstDef      ::= IDENT {"[" ConstExp "]"} "=" ConstInitVal;
+// ConstInitVal  ::= ConstExp | "{" [ConstInitVal {"," ConstInitVal}] "}";
+// VarDecl       ::= ["static" | "extern"] BType VarDef {"," VarDef} ";";
+// VarDef        ::= Pointers IDENT {"[" ConstExp "]"}
+//                 | Pointers IDENT {"[" ConstExp "]"} "=" InitVal;
+// InitVal       ::= Exp | "{" [InitVal {"," InitVal}] "}";
+
+// FuncDecl      ::= ["extern"] BType Pointers IDENT "(" [FuncFParams] ")" ";";
+// FuncDef       ::= BType Pointers IDENT "(" [FuncFParams] ")" Block;
 // FuncFParams   ::= FuncFParam {"," FuncFParam};
-// FuncFParam    ::= BType IDENT;
+// FuncFParam    ::= BType Pointers [IDENT] ["[" "]" {"[" ConstExp "]"}];
 
 // Block         ::= "{" {BlockItem} "}";
 // BlockItem     ::= Decl | Stmt;
@@ -19,33 +28,58 @@
 // OpenStmt      ::= "if" "(" Exp ")" Stmt
 //                 | "if" "(" Exp ")" ClosedStmt "else" OpenStmt;
 //                 | "while" "(" Exp ")" OpenStmt;
+//                 | "for" "(" ForInit [Exp] ";" [ForStep] ")" OpenStmt;
+//                 | IDENT ":" OpenStmt;
 // ClosedStmt    ::= SimpleStmt
 //                 | "if" "(" Exp ")" ClosedStmt "else" ClosedStmt;
 //                 | "while" "(" Exp ")" ClosedStmt;
+//                 | "for" "(" ForInit [Exp] ";" [ForStep] ")" ClosedStmt;
+//                 | "do" Stmt "while" "(" Exp ")" ";"
+//                 | "switch" "(" Exp ")" "{" {SwitchItem} "}"
+//                 | IDENT ":" ClosedStmt;
+// SwitchItem    ::= "case" ConstExp ":" | "default" ":" | BlockItem;
+// ForInit       ::= Decl | [ForStep] ";";
+// ForStep       ::= LVal "=" Exp | Exp;
 // SimpleStmt    ::= LVal "=" Exp ";"
 //                 | [Exp] ";"
 //                 | Block
 //                 | "break" ";"
 //                 | "continue" ";"
+//                 | "goto" IDENT ";"
 //                 | "return" [Exp] ";";
 
-// Exp           ::= LOrExp;
-// LVal          ::= IDENT;
-// PrimaryExp    ::= "(" Exp ")" | LVal | Number;
-// Number        ::= INT_CONST;
+// Exp           ::= AssignExp;
+// AssignExp     ::= CondExp | LVal AssignOp AssignExp;
+// AssignOp      ::= "+=" | "-=" | "*=" | "/=" | "%=";
+// LVal          ::= VarRef | "*" UnaryExp;
+// VarRef        ::= IDENT {"[" Exp "]"} {FieldAccess};
+// FieldAccess   ::= "." IDENT {"[" Exp "]"};
+// PrimaryExp    ::= "(" Exp ")" | LVal | Number | STRING_LITERAL;
+// Number        ::= INT_CONST | CHAR_CONST;
 // UnaryExp      ::= PrimaryExp 
 //                 | IDENT "(" [FuncRParams] ")"
-//                 | UnaryOp UnaryExp;
+//                 | UnaryOp UnaryExp
+//                 | "&" LVal
+//                 | IncDecOp LVal
+//                 | VarRef IncDecOp;
 // FuncRParams   ::= Exp {"," Exp};
-// UnaryOp       ::= "+" | "-" | "!";
+// UnaryOp       ::= "+" | "-" | "!" | "~";
+// IncDecOp      ::= "++" | "--";
 // MulExp        ::= UnaryExp | MulExp ("*" | "/" | "%") UnaryExp;
 // AddExp        ::= MulExp | AddExp ("+" | "-") MulExp;
-// RelExp        ::= AddExp | RelExp ("<" | ">" | "<=" | ">=") AddExp;
+// ShiftExp      ::= AddExp | ShiftExp ("<<" | ">>") AddExp;
+// RelExp        ::= ShiftExp | RelExp ("<" | ">" | "<=" | ">=") ShiftExp;
 // EqExp         ::= RelExp | EqExp ("==" | "!=") RelExp;
-// LAndExp       ::= EqExp | LAndExp "&&" EqExp;
+// BitAndExp     ::= EqExp | BitAndExp "&" EqExp;
+// BitXorExp     ::= BitAndExp | BitXorExp "^" BitAndExp;
+// BitOrExp      ::= BitXorExp | BitOrExp "|" BitXorExp;
+// LAndExp       ::= BitOrExp | LAndExp "&&" BitOrExp;
 // LOrExp        ::= LAndExp | LOrExp "||" LAndExp;
+// CondExp       ::= LOrExp | LOrExp "?" Exp ":" CondExp;
 // ConstExp      ::= Exp;
 
+use crate::span::Span;
+
 #[derive(Debug)]
 pub struct CompUnit {
     pub comp_unit_list: Vec<SimpleCompUnit>,
@@ -54,53 +88,127 @@ pub struct CompUnit {
 #[derive(Debug)]
 pub enum SimpleCompUnit {
     Decl(Decl),
+    FuncDecl(FuncDecl),
     FuncDef(FuncDef),
+    Error, // Placeholder for a syntax error the parser has recovered from.
 }
 
 #[derive(Debug)]
 pub enum Decl {
     ConstDecl(ConstDecl),
     VarDecl(VarDecl),
+    EnumDecl(EnumDecl),
+    StructDecl(StructDecl),
+    TypedefDecl(TypedefDecl),
 }
 
 #[derive(Debug)]
 pub struct ConstDecl {
+    pub is_static: bool,
     pub const_def_list: Vec<ConstDef>,
+    pub span: Span,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum BType {
     Int,
+    Char,
     Void,
+    Enum(String), // An enum type with the given tag, which is the same as `int`.
+    Struct(String), // A struct type with the given tag.
+    Pointer(Box<BType>),
+    Alias(String), // A type name defined by `typedef`.
+}
+
+impl BType {
+    /// Wraps the type in `pointers` levels of pointers.
+    pub fn with_pointers(self, pointers: usize) -> BType {
+        (0..pointers).fold(self, |b_type, _| BType::Pointer(Box::new(b_type)))
+    }
+}
+
+#[derive(Debug)]
+pub struct EnumDecl {
+    pub ident: Option<String>,
+    pub enumerator_list: Vec<Enumerator>,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct Enumerator {
+    pub ident: String,
+    pub const_exp: Option<ConstExp>,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct StructDecl {
+    pub ident: String,
+    pub field_list: Vec<StructField>,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct StructField {
+    pub b_type: BType,
+    pub ident: String,
+    pub dim_list: Vec<ConstExp>,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct TypedefDecl {
+    pub enum_decl: Option<EnumDecl>, // `Some` if the enum is defined in the typedef itself.
+    pub struct_decl: Option<StructDecl>, // Likewise for a struct.
+    pub b_type: BType,
+    pub ident: String,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct ConstDef {
     pub b_type: BType,
     pub ident: String,
+    pub dim_list: Vec<ConstExp>,
     pub const_init_val: Box<ConstInitVal>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
-pub struct ConstInitVal {
-    pub const_exp: Box<ConstExp>,
+pub enum ConstInitVal {
+    ConstExp(Box<ConstExp>),
+    List(Vec<ConstInitVal>),
 }
 
 #[derive(Debug)]
 pub struct VarDecl {
+    pub is_static: bool,
+    pub is_extern: bool, // Declares a global defined in another translation unit.
     pub var_def_list: Vec<VarDef>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct VarDef {
     pub b_type: BType,
     pub ident: String,
+    pub dim_list: Vec<ConstExp>,
     pub init_val: Box<Option<InitVal>>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
-pub struct InitVal {
-    pub exp: Box<Exp>,
+pub enum InitVal {
+    Exp(Box<Exp>),
+    List(Vec<InitVal>),
+}
+
+#[derive(Debug)]
+pub struct FuncDecl {
+    pub func_type: BType,
+    pub ident: String,
+    pub func_f_params: Option<FuncFParams>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -109,6 +217,7 @@ pub struct FuncDef {
     pub ident: String,
     pub func_f_params: Option<FuncFParams>,
     pub block: Block,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -119,7 +228,9 @@ pub struct FuncFParams {
 #[derive(Debug)]
 pub struct FuncFParam {
     pub b_type: BType,
-    pub ident: String,
+    pub ident: Option<String>, // `None` for an unnamed parameter.
+    pub dim_list: Option<Vec<ConstExp>>, // `Some` for an array parameter, holding all dimensions but the first.
+    pub span: Span,
 }
 
 // #[derive(Debug)]
@@ -131,6 +242,7 @@ pub struct FuncFParam {
 #[derive(Debug)]
 pub struct Block {
     pub block_item_list: Vec<BlockItem>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -147,36 +259,122 @@ pub enum Stmt {
 
 #[derive(Debug)]
 pub enum OpenStmt {
-    If(Box<Exp>, Box<Stmt>),
-    IfElse(Box<Exp>, Box<ClosedStmt>, Box<OpenStmt>),
-    While(Box<Exp>, Box<OpenStmt>),
+    If(Box<Exp>, Box<Stmt>, Span),
+    IfElse(Box<Exp>, Box<ClosedStmt>, Box<OpenStmt>, Span),
+    While(Box<Exp>, Box<OpenStmt>, Span),
+    For(Box<Option<ForInit>>, Box<Option<Exp>>, Box<Option<ForStep>>, Box<OpenStmt>, Span),
+    Labeled(String, Span, Box<OpenStmt>), // The span covers the label only.
 }
 
 #[derive(Debug)]
 pub enum ClosedStmt {
     SimpleStmt(SimpleStmt),
-    IfElse(Box<Exp>, Box<ClosedStmt>, Box<ClosedStmt>),
-    While(Box<Exp>, Box<ClosedStmt>),
+    IfElse(Box<Exp>, Box<ClosedStmt>, Box<ClosedStmt>, Span),
+    While(Box<Exp>, Box<ClosedStmt>, Span),
+    For(Box<Option<ForInit>>, Box<Option<Exp>>, Box<Option<ForStep>>, Box<ClosedStmt>, Span),
+    DoWhile(Box<Stmt>, Box<Exp>, Span),
+    Switch(Box<Exp>, Vec<SwitchItem>, Span),
+    Labeled(String, Span, Box<ClosedStmt>), // The span covers the label only.
+}
+
+#[derive(Debug)]
+pub enum SwitchItem {
+    Case(ConstExp),
+    Default(Span),
+    BlockItem(BlockItem),
+}
+
+#[derive(Debug)]
+pub enum ForInit {
+    Decl(Decl),
+    Step(ForStep),
+}
+
+#[derive(Debug)]
+pub enum ForStep {
+    Assign(LVal, Box<Exp>, Span),
+    Exp(Box<Exp>),
 }
 
 #[derive(Debug)]
 pub enum SimpleStmt {
-    Assign(LVal, Box<Exp>),
-    Exp(Box<Option<Exp>>),
+    Assign(LVal, Box<Exp>, Span),
+    Exp(Box<Option<Exp>>, Span),
     Block(Box<Block>),
-    Break,
-    Continue,
-    Return(Box<Option<Exp>>),
+    Break(Span),
+    Continue(Span),
+    Goto(String, Span),
+    Return(Box<Option<Exp>>, Span),
+    Error, // Placeholder for a syntax error the parser has recovered from.
 }
 
 #[derive(Debug)]
 pub struct Exp {
-    pub l_or_exp: LOrExp,
+    pub assign_exp: AssignExp,
+    pub span: Span,
+}
+
+impl Exp {
+    /// Returns the bytes of the string literal that the expression is, which
+    /// may be parenthesized.
+    pub fn as_string_literal(&self) -> Option<&[u8]> {
+        let AssignExp::CondExp(CondExp::LOrExp(LOrExp::LAndExp(LAndExp::BitOrExp(BitOrExp::BitXorExp(BitXorExp::BitAndExp(
+            BitAndExp::EqExp(EqExp::RelExp(RelExp::ShiftExp(ShiftExp::AddExp(AddExp::MulExp(MulExp::UnaryExp(UnaryExp::PrimaryExp(primary_exp)))))))
+        )))))) = &self.assign_exp else {
+            return None;
+        };
+        match primary_exp {
+            PrimaryExp::Exp(exp) => exp.as_string_literal(),
+            PrimaryExp::StringLiteral(string) => Some(string),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug)]
-pub struct LVal {
+pub enum AssignExp {
+    CondExp(CondExp),
+    CompoundAssign(LVal, AssignOp, Box<AssignExp>, Span),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum AssignOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
+#[derive(Debug)]
+pub enum LVal {
+    Var(VarRef),
+    Deref(Box<UnaryExp>, Span),
+}
+
+impl LVal {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Var(var) => var.span,
+            Self::Deref(_, span) => *span,
+        }
+    }
+}
+
+// A variable, possibly with subscripts and field accesses.
+#[derive(Debug)]
+pub struct VarRef {
+    pub ident: String,
+    pub index_list: Vec<Exp>,
+    pub field_list: Vec<FieldAccess>,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct FieldAccess {
     pub ident: String,
+    pub index_list: Vec<Exp>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -184,13 +382,17 @@ pub enum PrimaryExp {
     Exp(Box<Exp>),
     LVal(LVal),
     Number(i32),
+    StringLiteral(Vec<u8>), // The bytes after decoding the escape sequences, without the trailing NUL.
 }
 
 #[derive(Debug)]
 pub enum UnaryExp {
     PrimaryExp(PrimaryExp),
-    FuncCall(String, Option<FuncRParams>),
-    UnaryExp(UnaryOp, Box<UnaryExp>),
+    FuncCall(String, Option<FuncRParams>, Span),
+    UnaryExp(UnaryOp, Box<UnaryExp>, Span),
+    AddrOf(LVal, Span),
+    PrefixIncDec(IncDecOp, LVal, Span),
+    PostfixIncDec(LVal, IncDecOp, Span),
 }
 
 #[derive(Debug)]
@@ -203,52 +405,91 @@ pub enum UnaryOp {
     Plus,
     Minus,
     Not,
+    BitNot,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum IncDecOp {
+    Inc,
+    Dec,
 }
 
 #[derive(Debug)]
 pub enum MulExp {
     UnaryExp(UnaryExp),
-    Mul(Box<MulExp>, Box<UnaryExp>),
-    Div(Box<MulExp>, Box<UnaryExp>),
-    Mod(Box<MulExp>, Box<UnaryExp>),
+    Mul(Box<MulExp>, Box<UnaryExp>, Span),
+    Div(Box<MulExp>, Box<UnaryExp>, Span),
+    Mod(Box<MulExp>, Box<UnaryExp>, Span),
 }
 
 #[derive(Debug)]
 pub enum AddExp {
     MulExp(MulExp),
-    Add(Box<AddExp>, Box<MulExp>),
-    Sub(Box<AddExp>, Box<MulExp>),
+    Add(Box<AddExp>, Box<MulExp>, Span),
+    Sub(Box<AddExp>, Box<MulExp>, Span),
 }
 
 #[derive(Debug)]
-pub enum RelExp {
+pub enum ShiftExp {
     AddExp(AddExp),
-    Lt(Box<RelExp>, Box<AddExp>),
-    Gt(Box<RelExp>, Box<AddExp>),
-    Le(Box<RelExp>, Box<AddExp>),
-    Ge(Box<RelExp>, Box<AddExp>),
+    Shl(Box<ShiftExp>, Box<AddExp>, Span),
+    Sar(Box<ShiftExp>, Box<AddExp>, Span),
+}
+
+#[derive(Debug)]
+pub enum RelExp {
+    ShiftExp(ShiftExp),
+    Lt(Box<RelExp>, Box<ShiftExp>, Span),
+    Gt(Box<RelExp>, Box<ShiftExp>, Span),
+    Le(Box<RelExp>, Box<ShiftExp>, Span),
+    Ge(Box<RelExp>, Box<ShiftExp>, Span),
 }
 
 #[derive(Debug)]
 pub enum EqExp {
     RelExp(RelExp),
-    Eq(Box<EqExp>, Box<RelExp>),
-    Ne(Box<EqExp>, Box<RelExp>),
+    Eq(Box<EqExp>, Box<RelExp>, Span),
+    Ne(Box<EqExp>, Box<RelExp>, Span),
 }
 
 #[derive(Debug)]
-pub enum LAndExp {
+pub enum BitAndExp {
     EqExp(EqExp),
-    And(Box<LAndExp>, Box<EqExp>),
+    BitAnd(Box<BitAndExp>, Box<EqExp>, Span),
+}
+
+#[derive(Debug)]
+pub enum BitXorExp {
+    BitAndExp(BitAndExp),
+    BitXor(Box<BitXorExp>, Box<BitAndExp>, Span),
+}
+
+#[derive(Debug)]
+pub enum BitOrExp {
+    BitXorExp(BitXorExp),
+    BitOr(Box<BitOrExp>, Box<BitXorExp>, Span),
+}
+
+#[derive(Debug)]
+pub enum LAndExp {
+    BitOrExp(BitOrExp),
+    And(Box<LAndExp>, Box<BitOrExp>, Span),
 }
 
 #[derive(Debug)]
 pub enum LOrExp {
     LAndExp(LAndExp),
-    Or(Box<LOrExp>, Box<LAndExp>),
+    Or(Box<LOrExp>, Box<LAndExp>, Span),
+}
+
+#[derive(Debug)]
+pub enum CondExp {
+    LOrExp(LOrExp),
+    Cond(Box<LOrExp>, Box<Exp>, Box<CondExp>, Span),
 }
 
 #[derive(Debug)]
 pub struct ConstExp {
     pub exp: Box<Exp>,
+    pub span: Span,
 }
@@ -1,4 +1,4 @@
-use koopa::ir::{BasicBlock, BinaryOp, Value, ValueKind};
+use koopa::ir::{BasicBlock, BinaryOp, Function, TypeKind, Value, ValueKind};
 
 use super::env::CodegenEnv;
 
@@ -7,12 +7,16 @@ pub fn generate_li(riscv_text: &mut String, dest: &str, imm: i32) {
     riscv_text.push_str(&format!("  li {}, {}\n", dest, imm));
 }
 
+pub fn generate_mv(riscv_text: &mut String, dest: &str, src: &str) {
+    riscv_text.push_str(&format!("  mv {}, {}\n", dest, src));
+}
+
 pub fn generate_add(riscv_text: &mut String, dest: &str, src1: &str, src2: &str) {
     riscv_text.push_str(&format!("  add {}, {}, {}\n", dest, src1, src2));
 }
 
 pub fn generate_addi(riscv_text: &mut String, dest: &str, src: &str, imm: i32) {
-    assert!(imm >= -2048 && imm < 2048);
+    assert!((-2048..2048).contains(&imm));
     riscv_text.push_str(&format!("  addi {}, {}, {}\n", dest, src, imm));
 }
 
@@ -40,6 +44,38 @@ pub fn generate_or(riscv_text: &mut String, dest: &str, src1: &str, src2: &str)
     riscv_text.push_str(&format!("  or {}, {}, {}\n", dest, src1, src2));
 }
 
+pub fn generate_xor(riscv_text: &mut String, dest: &str, src1: &str, src2: &str) {
+    riscv_text.push_str(&format!("  xor {}, {}, {}\n", dest, src1, src2));
+}
+
+pub fn generate_sll(riscv_text: &mut String, dest: &str, src1: &str, src2: &str) {
+    riscv_text.push_str(&format!("  sll {}, {}, {}\n", dest, src1, src2));
+}
+
+pub fn generate_srl(riscv_text: &mut String, dest: &str, src1: &str, src2: &str) {
+    riscv_text.push_str(&format!("  srl {}, {}, {}\n", dest, src1, src2));
+}
+
+pub fn generate_sra(riscv_text: &mut String, dest: &str, src1: &str, src2: &str) {
+    riscv_text.push_str(&format!("  sra {}, {}, {}\n", dest, src1, src2));
+}
+
+/// Emits the immediate form of `op` (`andi`, `slli`, ...) if it has one that fits `imm`.
+pub fn generate_binary_imm(riscv_text: &mut String, op: BinaryOp, dest: &str, src: &str, imm: i32) -> bool {
+    let (inst, imm) = match op {
+        BinaryOp::And if (-2048..2048).contains(&imm) => ("andi", imm),
+        BinaryOp::Or if (-2048..2048).contains(&imm) => ("ori", imm),
+        BinaryOp::Xor if (-2048..2048).contains(&imm) => ("xori", imm),
+        // Only the low 5 bits of the shift amount matter, just like the register forms.
+        BinaryOp::Shl => ("slli", imm & 31),
+        BinaryOp::Shr => ("srli", imm & 31),
+        BinaryOp::Sar => ("srai", imm & 31),
+        _ => return false,
+    };
+    riscv_text.push_str(&format!("  {} {}, {}, {}\n", inst, dest, src, imm));
+    true
+}
+
 pub fn generate_eq(riscv_text: &mut String, dest: &str, src1: &str, src2: &str) {
     riscv_text.push_str(&format!("  xor {}, {}, {}\n", dest, src1, src2));
     riscv_text.push_str(&format!("  seqz {}, {}\n", dest, dest));
@@ -68,13 +104,17 @@ pub fn generate_ge(riscv_text: &mut String, dest: &str, src1: &str, src2: &str)
     riscv_text.push_str(&format!("  seqz {}, {}\n", dest, dest));
 }
 
+pub fn generate_la(riscv_text: &mut String, dest: &str, symbol: &str) {
+    riscv_text.push_str(&format!("  la {}, {}\n", dest, symbol));
+}
+
 pub fn generate_lw(riscv_text: &mut String, dest: &str, base: &str, offset: i32) {
-    assert!(offset >= -2048 && offset < 2048);
+    assert!((-2048..2048).contains(&offset));
     riscv_text.push_str(&format!("  lw {}, {}({})\n", dest, offset, base));
 }
 
 pub fn generate_sw(riscv_text: &mut String, src: &str, base: &str, offset: i32) {
-    assert!(offset >= -2048 && offset < 2048);
+    assert!((-2048..2048).contains(&offset));
     riscv_text.push_str(&format!("  sw {}, {}({})\n", src, offset, base));
 }
 
@@ -86,8 +126,20 @@ pub fn generate_j(riscv_text: &mut String, bb: &str) {
     riscv_text.push_str(&format!("  j {}\n", bb));
 }
 
+pub fn generate_bgeu(riscv_text: &mut String, src1: &str, src2: &str, bb: &str) {
+    riscv_text.push_str(&format!("  bgeu {}, {}, {}\n", src1, src2, bb));
+}
+
+pub fn generate_call(riscv_text: &mut String, func: &str) {
+    riscv_text.push_str(&format!("  call {}\n", func));
+}
+
+pub fn generate_jr(riscv_text: &mut String, src: &str) {
+    riscv_text.push_str(&format!("  jr {}\n", src));
+}
+
 pub fn generate_addi_with_any_imm(riscv_text: &mut String, dest: &str, src: &str, tmp: &str, imm: i32) {
-    if imm >= -2048 && imm < 2048 {
+    if (-2048..2048).contains(&imm) {
         generate_addi(riscv_text, dest, src, imm);
     } else {
         generate_li(riscv_text, tmp, imm);
@@ -96,7 +148,7 @@ pub fn generate_addi_with_any_imm(riscv_text: &mut String, dest: &str, src: &str
 }
 
 pub fn generate_lw_with_any_offset(riscv_text: &mut String, dest: &str, base: &str, tmp: &str, offset: i32) {
-    if offset >= -2048 && offset < 2048 {
+    if (-2048..2048).contains(&offset) {
         generate_lw(riscv_text, dest, base, offset);
     } else {
         generate_li(riscv_text, tmp, offset);
@@ -106,7 +158,7 @@ pub fn generate_lw_with_any_offset(riscv_text: &mut String, dest: &str, base: &s
 }
 
 pub fn generate_sw_with_any_offset(riscv_text: &mut String, src: &str, base: &str, tmp: &str, offset: i32) {
-    if offset >= -2048 && offset < 2048 {
+    if (-2048..2048).contains(&offset) {
         generate_sw(riscv_text, src, base, offset);
     } else {
         generate_li(riscv_text, tmp, offset);
@@ -115,48 +167,126 @@ pub fn generate_sw_with_any_offset(riscv_text: &mut String, src: &str, base: &st
     }
 }
 
+/// Puts the address held by the pointer `ptr` into `dest`.
+pub fn generate_address(riscv_text: &mut String, env: &CodegenEnv, ptr: Value, dest: &str, tmp: &str) {
+    if ptr.is_global() {
+        generate_la(riscv_text, dest, &env.get_global_name(ptr));
+        return;
+    }
+    let offset = env.get_frame_size() - env.get_offset(ptr).unwrap();
+    match env.get_value_data(ptr).kind() {
+        ValueKind::Alloc(_) => generate_addi_with_any_imm(riscv_text, dest, "sp", tmp, offset),
+        _ => generate_lw_with_any_offset(riscv_text, dest, "sp", tmp, offset),
+    }
+}
+
 pub fn generate_load(riscv_text: &mut String, env: &CodegenEnv, src: Value, dest: Value, tmp1: &str, tmp2: &str) {
-    let offset = env.get_frame_size() - env.get_offset(src).unwrap();
-    generate_lw_with_any_offset(riscv_text, tmp1, "sp", tmp2, offset);
+    if !src.is_global() && matches!(env.get_value_data(src).kind(), ValueKind::Alloc(_)) {
+        let offset = env.get_frame_size() - env.get_offset(src).unwrap();
+        generate_lw_with_any_offset(riscv_text, tmp1, "sp", tmp2, offset);
+    } else {
+        generate_address(riscv_text, env, src, tmp1, tmp2);
+        generate_lw(riscv_text, tmp1, tmp1, 0);
+    }
     let offset = env.get_frame_size() - env.get_offset(dest).unwrap();
     generate_sw_with_any_offset(riscv_text, tmp1, "sp", tmp2, offset);
 }
 
-pub fn generate_store(riscv_text: &mut String, env: &CodegenEnv, src: Value, dest: Value, tmp1: &str, tmp2: &str) {
-    let src_data = env.get_value_data(src);
-    match src_data.kind() {
+/// Puts the integer or pointer `value` into `dest`, wherever it lives.
+pub fn generate_value(riscv_text: &mut String, env: &CodegenEnv, value: Value, dest: &str, tmp: &str) {
+    // An allocation used as an operand is its address, as in `&x`.
+    if value.is_global() {
+        generate_la(riscv_text, dest, &env.get_global_name(value));
+        return;
+    }
+    match env.get_value_data(value).kind() {
+        ValueKind::Alloc(_) => {
+            let offset = env.get_frame_size() - env.get_offset(value).unwrap();
+            generate_addi_with_any_imm(riscv_text, dest, "sp", tmp, offset);
+        }
         ValueKind::Integer(i) => {
-            generate_li(riscv_text, tmp1, i.value());
+            generate_li(riscv_text, dest, i.value());
+        }
+        // The first 8 arguments come in `a0`-`a7`, and the rest are at the
+        // bottom of the caller's frame, just above ours.
+        ValueKind::FuncArgRef(arg) if arg.index() < 8 => {
+            generate_mv(riscv_text, dest, &format!("a{}", arg.index()));
+        }
+        ValueKind::FuncArgRef(arg) => {
+            let offset = env.get_frame_size() + (arg.index() as i32 - 8) * 4;
+            generate_lw_with_any_offset(riscv_text, dest, "sp", tmp, offset);
         }
         _ => {
-            let offset = env.get_frame_size() - env.get_offset(src).unwrap();
-            generate_lw_with_any_offset(riscv_text, tmp1, "sp", tmp2, offset);
+            let offset = env.get_frame_size() - env.get_offset(value).unwrap();
+            generate_lw_with_any_offset(riscv_text, dest, "sp", tmp, offset);
         }
     }
-    let offset = env.get_frame_size() - env.get_offset(dest).unwrap();
-    generate_sw_with_any_offset(riscv_text, tmp1, "sp", tmp2, offset);
 }
 
-pub fn generate_binary(riscv_text: &mut String, env: &CodegenEnv, op: BinaryOp, lhs: Value, rhs: Value, dest: Value, tmp1: &str, tmp2: &str, tmp3: &str, tmp4: &str) {
-    let lhs_data = env.get_value_data(lhs);
-    match lhs_data.kind() {
+pub fn generate_store(riscv_text: &mut String, env: &CodegenEnv, src: Value, dest: Value, tmp1: &str, tmp2: &str) {
+    generate_value(riscv_text, env, src, tmp1, tmp2);
+    if !dest.is_global() && matches!(env.get_value_data(dest).kind(), ValueKind::Alloc(_)) {
+        let offset = env.get_frame_size() - env.get_offset(dest).unwrap();
+        generate_sw_with_any_offset(riscv_text, tmp1, "sp", tmp2, offset);
+    } else {
+        generate_address(riscv_text, env, dest, tmp2, tmp2);
+        generate_sw(riscv_text, tmp1, tmp2, 0);
+    }
+}
+
+/// Generates `dest = src + index * stride`, which is how both `getptr` and
+/// `getelemptr` work once the stride is known.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_get_ptr(riscv_text: &mut String, env: &CodegenEnv, src: Value, index: Value, stride: i32, dest: Value, tmp1: &str, tmp2: &str, tmp3: &str) {
+    generate_address(riscv_text, env, src, tmp1, tmp3);
+    let index_data = env.get_value_data(index);
+    match index_data.kind() {
         ValueKind::Integer(i) => {
-            generate_li(riscv_text, tmp1, i.value());
+            generate_addi_with_any_imm(riscv_text, tmp1, tmp1, tmp2, i.value() * stride);
         }
         _ => {
-            let offset = env.get_frame_size() - env.get_offset(lhs).unwrap();
-            generate_lw_with_any_offset(riscv_text, tmp1, "sp", tmp3, offset);
+            let offset = env.get_frame_size() - env.get_offset(index).unwrap();
+            generate_lw_with_any_offset(riscv_text, tmp2, "sp", tmp3, offset);
+            generate_li(riscv_text, tmp3, stride);
+            generate_mul(riscv_text, tmp2, tmp2, tmp3);
+            generate_add(riscv_text, tmp1, tmp1, tmp2);
         }
     }
+    let offset = env.get_frame_size() - env.get_offset(dest).unwrap();
+    generate_sw_with_any_offset(riscv_text, tmp1, "sp", tmp2, offset);
+}
+
+pub fn get_pointee_size(env: &CodegenEnv, ptr: Value) -> i32 {
+    match env.get_value_type(ptr).kind() {
+        TypeKind::Pointer(base) => base.size() as i32,
+        _ => unreachable!(),
+    }
+}
+
+pub fn get_elem_size(env: &CodegenEnv, ptr: Value) -> i32 {
+    match env.get_value_type(ptr).kind() {
+        TypeKind::Pointer(base) => match base.kind() {
+            TypeKind::Array(elem, _) => elem.size() as i32,
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn generate_binary(riscv_text: &mut String, env: &CodegenEnv, op: BinaryOp, lhs: Value, rhs: Value, dest: Value, tmp1: &str, tmp2: &str, tmp3: &str, tmp4: &str) {
+    generate_value(riscv_text, env, lhs, tmp1, tmp3);
     let rhs_data = env.get_value_data(rhs);
     match rhs_data.kind() {
         ValueKind::Integer(i) => {
+            if generate_binary_imm(riscv_text, op, tmp1, tmp1, i.value()) {
+                let offset = env.get_frame_size() - env.get_offset(dest).unwrap();
+                generate_sw_with_any_offset(riscv_text, tmp1, "sp", tmp3, offset);
+                return;
+            }
             generate_li(riscv_text, tmp2, i.value());
         }
-        _ => {
-            let offset = env.get_frame_size() - env.get_offset(rhs).unwrap();
-            generate_lw_with_any_offset(riscv_text, tmp2, "sp", tmp4, offset);
-        }
+        _ => generate_value(riscv_text, env, rhs, tmp2, tmp4),
     }
     match op {
         BinaryOp::Add => generate_add(riscv_text, tmp1, tmp1, tmp2),
@@ -166,13 +296,16 @@ pub fn generate_binary(riscv_text: &mut String, env: &CodegenEnv, op: BinaryOp,
         BinaryOp::Mod => generate_mod(riscv_text, tmp1, tmp1, tmp2),
         BinaryOp::And => generate_and(riscv_text, tmp1, tmp1, tmp2),
         BinaryOp::Or => generate_or(riscv_text, tmp1, tmp1, tmp2),
+        BinaryOp::Xor => generate_xor(riscv_text, tmp1, tmp1, tmp2),
+        BinaryOp::Shl => generate_sll(riscv_text, tmp1, tmp1, tmp2),
+        BinaryOp::Shr => generate_srl(riscv_text, tmp1, tmp1, tmp2),
+        BinaryOp::Sar => generate_sra(riscv_text, tmp1, tmp1, tmp2),
         BinaryOp::Eq => generate_eq(riscv_text, tmp1, tmp1, tmp2),
         BinaryOp::NotEq => generate_ne(riscv_text, tmp1, tmp1, tmp2),
         BinaryOp::Lt => generate_lt(riscv_text, tmp1, tmp1, tmp2),
         BinaryOp::Gt => generate_gt(riscv_text, tmp1, tmp1, tmp2),
         BinaryOp::Le => generate_le(riscv_text, tmp1, tmp1, tmp2),
         BinaryOp::Ge => generate_ge(riscv_text, tmp1, tmp1, tmp2),
-        _ => unimplemented!()
     }
     let offset = env.get_frame_size() - env.get_offset(dest).unwrap();
     generate_sw_with_any_offset(riscv_text, tmp1, "sp", tmp3, offset);
@@ -180,8 +313,8 @@ pub fn generate_binary(riscv_text: &mut String, env: &CodegenEnv, op: BinaryOp,
 
 pub fn generate_branch(riscv_text: &mut String, env: &CodegenEnv, cond: Value, bb_true: BasicBlock, bb_false: BasicBlock, tmp1: &str, tmp2: &str) {
     let cond_data = env.get_value_data(cond);
-    let label_true = &env.get_label(bb_true)[1..];
-    let label_false = &env.get_label(bb_false)[1..];
+    let label_true = env.get_label(bb_true);
+    let label_false = env.get_label(bb_false);
     match cond_data.kind() {
         ValueKind::Integer(i) => {
             if i.value() != 0 {
@@ -193,28 +326,133 @@ pub fn generate_branch(riscv_text: &mut String, env: &CodegenEnv, cond: Value, b
         _ => {
             let offset = env.get_frame_size() - env.get_offset(cond).unwrap();
             generate_lw_with_any_offset(riscv_text, tmp1, "sp", tmp2, offset);
-            generate_bnez(riscv_text, tmp1, label_true);
-            generate_j(riscv_text, label_false); 
+            generate_bnez(riscv_text, tmp1, &label_true);
+            generate_j(riscv_text, &label_false); 
         }
     }
 }
 
 pub fn generate_jump(riscv_text: &mut String, env: &CodegenEnv, bb: BasicBlock) {
-    let label = &env.get_label(bb)[1..];
-    generate_j(riscv_text, label);
+    generate_j(riscv_text, &env.get_label(bb));
 }
 
-pub fn generate_return(riscv_text: &mut String, env: &CodegenEnv, ret: Value, dest: &str, tmp: &str) {
-    let ret_data = env.get_value_data(ret);
-    match ret_data.kind() {
-        ValueKind::Integer(i) => {
-            generate_li(riscv_text, "a0", i.value());
-        }
+/// Jumps to the target of `cond` through a table of labels placed in `.rodata`.
+/// `cases` must be sorted by value.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_jump_table(riscv_text: &mut String, env: &CodegenEnv, cond: Value, cases: &[(i32, BasicBlock)], default_bb: BasicBlock, table: &str, tmp1: &str, tmp2: &str) {
+    let min = cases.first().unwrap().0;
+    let max = cases.last().unwrap().0;
+    let default_label = &env.get_label(default_bb);
+    match env.get_value_data(cond).kind() {
+        ValueKind::Integer(i) => generate_li(riscv_text, tmp1, i.value()),
         _ => {
-            let offset = env.get_frame_size() - env.get_offset(ret).unwrap();
-            generate_lw_with_any_offset(riscv_text, dest, "sp", tmp, offset);
+            let offset = env.get_frame_size() - env.get_offset(cond).unwrap();
+            generate_lw_with_any_offset(riscv_text, tmp1, "sp", tmp2, offset);
+        }
+    }
+    // Values below `min` wrap around to large unsigned numbers, so one
+    // unsigned comparison covers both ends of the range.
+    generate_li(riscv_text, tmp2, min);
+    generate_sub(riscv_text, tmp1, tmp1, tmp2);
+    generate_li(riscv_text, tmp2, max.wrapping_sub(min).wrapping_add(1));
+    generate_bgeu(riscv_text, tmp1, tmp2, default_label);
+    generate_la(riscv_text, tmp2, &format!("{}.labels", table));
+    riscv_text.push_str(&format!("  slli {}, {}, 2\n", tmp1, tmp1));
+    generate_add(riscv_text, tmp2, tmp2, tmp1);
+    generate_lw(riscv_text, tmp2, tmp2, 0);
+    generate_jr(riscv_text, tmp2);
+
+    riscv_text.push_str("  .section .rodata\n");
+    riscv_text.push_str(&format!("{}.labels:\n", table));
+    let mut cases = cases.iter().peekable();
+    for val in min..=max {
+        match cases.next_if(|&&(case_val, _)| case_val == val) {
+            Some(&(_, bb)) => riscv_text.push_str(&format!("  .word {}\n", env.get_label(bb))),
+            None => riscv_text.push_str(&format!("  .word {}\n", default_label)),
+        }
+    }
+    riscv_text.push_str("  .text\n");
+}
+
+/// Generates a call of `func` with `args`, storing the result into `dest` if
+/// the callee returns one.
+///
+/// Arguments to variadic functions follow the same rules as the others in
+/// the RISC-V calling convention, except for values wider than 32 bits, which
+/// SysY does not have: the first 8 go in `a0`-`a7`, and the rest are stored
+/// at the bottom of the frame, the first of them at `0(sp)`.
+pub fn generate_func_call(riscv_text: &mut String, env: &CodegenEnv, func: &str, args: &[Value], dest: Value, tmp1: &str, tmp2: &str) {
+    for (i, &arg) in args.iter().enumerate() {
+        if i < 8 {
+            generate_value(riscv_text, env, arg, &format!("a{}", i), tmp1);
+        } else {
+            generate_value(riscv_text, env, arg, tmp1, tmp2);
+            generate_sw_with_any_offset(riscv_text, tmp1, "sp", tmp2, (i as i32 - 8) * 4);
         }
-    }    
+    }
+    generate_call(riscv_text, func);
+    generate_call_result(riscv_text, env, dest, tmp1);
+}
+
+/// Calls a variadic function, whose last two arguments in the IR are an array
+/// of the extra arguments and its length. The extra arguments are passed on
+/// after the others, as the C calling convention has them.
+pub fn generate_variadic_call(riscv_text: &mut String, env: &CodegenEnv, func: &str, args: &[Value], dest: Value, tmp1: &str, tmp2: &str) {
+    let (fixed, extra) = args.split_at(args.len() - 2);
+    let extra_len = get_extra_args_len(env, extra[1]);
+    for (i, &arg) in fixed.iter().enumerate() {
+        if i < 8 {
+            generate_value(riscv_text, env, arg, &format!("a{}", i), tmp1);
+        } else {
+            generate_value(riscv_text, env, arg, tmp1, tmp2);
+            generate_sw_with_any_offset(riscv_text, tmp1, "sp", tmp2, (i as i32 - 8) * 4);
+        }
+    }
+    for k in 0..extra_len {
+        let i = fixed.len() + k;
+        let reg = if i < 8 { format!("a{}", i) } else { tmp1.to_string() };
+        generate_value(riscv_text, env, extra[0], &reg, tmp2);
+        generate_lw_with_any_offset(riscv_text, &reg, &reg, tmp2, k as i32 * 4);
+        if i >= 8 {
+            generate_sw_with_any_offset(riscv_text, tmp1, "sp", tmp2, (i as i32 - 8) * 4);
+        }
+    }
+    generate_call(riscv_text, func);
+    generate_call_result(riscv_text, env, dest, tmp1);
+}
+
+/// Stores the result of a call, if it is used.
+fn generate_call_result(riscv_text: &mut String, env: &CodegenEnv, dest: Value, tmp: &str) {
+    if let Some(offset) = env.get_offset(dest) {
+        let offset = env.get_frame_size() - offset;
+        generate_sw_with_any_offset(riscv_text, "a0", "sp", tmp, offset);
+    }
+}
+
+/// Returns how many arguments a call passes in registers and on the stack,
+/// counting the extra arguments of a variadic function one by one.
+pub fn get_args_len(env: &CodegenEnv, func: Function, args: &[Value]) -> usize {
+    if env.is_func_variadic(func) {
+        args.len() - 2 + get_extra_args_len(env, args[args.len() - 1])
+    } else {
+        args.len()
+    }
+}
+
+fn get_extra_args_len(env: &CodegenEnv, len: Value) -> usize {
+    match env.get_value_data(len).kind() {
+        ValueKind::Integer(i) => i.value() as usize,
+        _ => unreachable!(),
+    }
+}
+
+pub fn generate_return(riscv_text: &mut String, env: &CodegenEnv, ret: Option<Value>, tmp: &str) {
+    if let Some(ret) = ret {
+        generate_value(riscv_text, env, ret, "a0", tmp);
+    }
+    if let Some(ra_offset) = env.get_ra_offset() {
+        generate_lw_with_any_offset(riscv_text, "ra", "sp", tmp, env.get_frame_size() - ra_offset);
+    }
     generate_addi_with_any_imm(riscv_text, "sp", "sp", tmp, env.get_frame_size());
     riscv_text.push_str("  ret\n");
-}
\ No newline at end of file
+}
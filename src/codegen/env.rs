@@ -1,17 +1,41 @@
 use std::collections::HashMap;
 
 use koopa::ir::entities::{Function, ValueData};
-use koopa::ir::{BasicBlock, Program, Value};
+use koopa::ir::{BasicBlock, Program, Type, Value};
+
+use crate::irgen::{JumpTable, Module};
 
 pub struct CodegenEnv<'p> {
+    module: &'p Module,
     program: &'p Program,
     cur_func: Option<Function>,
     stack_info: StackInfo,
 }
 
-impl<'p, 'f> CodegenEnv<'p> {
-    pub fn new(program: &'p Program) -> Self {
-        Self { program, cur_func: None , stack_info: StackInfo::new() }
+impl<'p> CodegenEnv<'p> {
+    pub fn new(module: &'p Module) -> Self {
+        Self { module, program: &module.program, cur_func: None , stack_info: StackInfo::new() }
+    }
+
+    pub fn get_program(&self) -> &'p Program {
+        self.program
+    }
+
+    /// Returns the jump table of the current function whose chain starts
+    /// with `bb`.
+    pub fn get_jump_table(&self, bb: BasicBlock) -> Option<&'p JumpTable> {
+        let jump_tables = self.module.jump_tables.get(&self.cur_func.unwrap())?;
+        jump_tables.iter().find(|jump_table| jump_table.chain[0] == bb)
+    }
+
+    /// Whether `bb` is in the chain of a jump table of the current function.
+    pub fn is_in_jump_table(&self, bb: BasicBlock) -> bool {
+        let jump_tables = self.module.jump_tables.get(&self.cur_func.unwrap());
+        jump_tables.into_iter().flatten().any(|jump_table| jump_table.chain.contains(&bb))
+    }
+
+    pub fn is_func_variadic(&self, func: Function) -> bool {
+        self.module.variadic_funcs.contains(&func)
     }
 
     pub fn set_cur_func(&mut self, func: Function) {
@@ -30,6 +54,14 @@ impl<'p, 'f> CodegenEnv<'p> {
         self.stack_info.get_offset(value)
     }
 
+    pub fn set_ra_offset(&mut self, ra_offset: Option<i32>) {
+        self.stack_info.set_ra_offset(ra_offset);
+    }
+
+    pub fn get_ra_offset(&self) -> Option<i32> {
+        self.stack_info.get_ra_offset()
+    }
+
     pub fn set_offset(&mut self, value: Value, offset: i32) {
         self.stack_info.set_offset(value, offset);
     }
@@ -40,21 +72,40 @@ impl<'p, 'f> CodegenEnv<'p> {
         cur_func_data.dfg().value(value)
     }
 
-    pub fn get_label(&self, bb: BasicBlock) -> &str {
+    pub fn get_value_type(&self, value: Value) -> Type {
+        if value.is_global() {
+            self.program.borrow_value(value).ty().clone()
+        } else {
+            self.get_value_data(value).ty().clone()
+        }
+    }
+
+    pub fn get_global_name(&self, value: Value) -> String {
+        let name = self.program.borrow_value(value).name().clone().unwrap();
+        name[1..].to_string()
+    }
+
+    /// Returns the assembly label of `bb`, which is prefixed with the function
+    /// name since every function has blocks like `%entry`. The `.L` prefix
+    /// keeps it out of the symbol table, and the `.` separator keeps it apart
+    /// from the labels of a function like `f_entry`.
+    pub fn get_label(&self, bb: BasicBlock) -> String {
         let cur_func = self.cur_func.unwrap();
         let cur_func_data = self.program.func(cur_func);
-        cur_func_data.dfg().bb(bb).name().as_ref().unwrap()
+        let bb_name = cur_func_data.dfg().bb(bb).name().as_ref().unwrap();
+        format!(".L{}.{}", &cur_func_data.name()[1..], &bb_name[1..])
     }
 }
 
 pub struct StackInfo {
     frame_size: i32,
     offset_table: HashMap<Value, i32>,
+    ra_offset: Option<i32>, // Only functions that make calls save `ra`.
 }
 
 impl StackInfo {
     pub fn new() -> Self {
-        Self { frame_size: 0, offset_table: HashMap::new() }
+        Self { frame_size: 0, offset_table: HashMap::new(), ra_offset: None }
     }
 
     pub fn set_frame_size(&mut self, frame_size: i32) {
@@ -72,4 +123,12 @@ impl StackInfo {
     pub fn set_offset(&mut self, value: Value, offset: i32) {
         self.offset_table.insert(value, offset);
     }
+
+    pub fn get_ra_offset(&self) -> Option<i32> {
+        self.ra_offset
+    }
+
+    pub fn set_ra_offset(&mut self, ra_offset: Option<i32>) {
+        self.ra_offset = ra_offset;
+    }
 }
\ No newline at end of file
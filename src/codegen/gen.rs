@@ -1,9 +1,11 @@
 use super::asmutil::*;
 use super::CodegenError;
 use super::env::CodegenEnv;
+use crate::irgen::Module;
 use koopa::ir::Value;
 use koopa::ir::TypeKind;
-use koopa::ir::{FunctionData, Program, ValueKind};
+use koopa::ir::{FunctionData, ValueKind};
+use koopa::ir::entities::ValueData;
 use std::result::Result;
 
 pub trait GenerateAsm<'p> {
@@ -12,14 +14,38 @@ pub trait GenerateAsm<'p> {
     fn generate_riscv(&self, riscv_text: &mut String, env: &mut CodegenEnv<'p>) -> Result<Self::Out, CodegenError>;
 }
 
-impl<'p> GenerateAsm<'p> for Program {
+impl<'p> GenerateAsm<'p> for Module {
     type Out = ();
     
     fn generate_riscv(&self, riscv_text: &mut String, env: &mut CodegenEnv<'p>) -> Result<Self::Out, CodegenError> {
+        let program = &self.program;
+        for &global in program.inst_layout() {
+            let global_data = program.borrow_value(global);
+            let name = &global_data.name().as_ref().unwrap()[1..];
+            // String literals are read-only, and they and static variables
+            // are private to their translation unit.
+            if self.string_globals.contains(&global) {
+                riscv_text.push_str("  .section .rodata\n");
+            } else {
+                riscv_text.push_str("  .data\n");
+            }
+            if !self.string_globals.contains(&global) && !self.static_globals.contains(&global) {
+                riscv_text.push_str(&format!("  .globl {}\n", name));
+            }
+            riscv_text.push_str(&format!("{}:\n", name));
+            if let ValueKind::GlobalAlloc(global_alloc) = global_data.kind() {
+                program.borrow_value(global_alloc.init()).generate_riscv(riscv_text, env)?;
+            }
+            riscv_text.push('\n');
+        }
+
         riscv_text.push_str("  .text\n");
-        for &func in self.func_layout() {
+        for &func in program.func_layout() {
+            if program.func(func).layout().entry_bb().is_none() {
+                continue;
+            }
             env.set_cur_func(func);
-            self.func(func).generate_riscv(riscv_text, env)?;
+            program.func(func).generate_riscv(riscv_text, env)?;
         }
         Ok(())
     }
@@ -33,11 +59,35 @@ impl<'p> GenerateAsm<'p> for FunctionData {
         riscv_text.push_str(&format!("  .globl {}\n", func_name));
         riscv_text.push_str(&format!("{}:\n", func_name));
 
+        // The frame holds, from top to bottom, the saved `ra` (only if the
+        // function makes calls), the values and the arguments passed on the
+        // stack.
         let mut frame_offset= 0;
+        let mut has_call = false;
+        let mut max_stack_args = 0;
+        for (&_bb, node) in self.layout().bbs() {
+            for &inst in node.insts().keys() {
+                if let ValueKind::Call(call) = self.dfg().value(inst).kind() {
+                    has_call = true;
+                    let args_len = get_args_len(env, call.callee(), call.args());
+                    max_stack_args = max_stack_args.max(args_len.saturating_sub(8) as i32);
+                }
+            }
+        }
+        if has_call {
+            frame_offset += 4;
+            env.set_ra_offset(Some(frame_offset));
+        } else {
+            env.set_ra_offset(None);
+        }
         for (&_bb, node) in self.layout().bbs() {
             for &inst in node.insts().keys() {
                 let value_data = self.dfg().value(inst);
                 match value_data.ty().kind() {
+                    TypeKind::Pointer(base) if matches!(value_data.kind(), ValueKind::Alloc(_)) => {
+                        frame_offset += base.size() as i32;
+                        env.set_offset(inst, frame_offset);
+                    },
                     TypeKind::Int32 | TypeKind::Pointer(_) => {
                         frame_offset += 4;
                         env.set_offset(inst, frame_offset);
@@ -47,12 +97,26 @@ impl<'p> GenerateAsm<'p> for FunctionData {
                 }
             }
         }
+        frame_offset += max_stack_args * 4;
         frame_offset = (frame_offset + 15) & !15;
         env.set_frame_size(frame_offset);
         generate_addi_with_any_imm(riscv_text, "sp", "sp", "t0", -frame_offset);
+        if let Some(ra_offset) = env.get_ra_offset() {
+            generate_sw_with_any_offset(riscv_text, "ra", "sp", "t0", frame_offset - ra_offset);
+        }
 
         for (&bb, node) in self.layout().bbs() {
-            let label = &env.get_label(bb)[1..];
+            let label = env.get_label(bb);
+            if let Some(jump_table) = env.get_jump_table(bb) {
+                riscv_text.push_str(&format!("{}:\n", label));
+                generate_jump_table(riscv_text, env, jump_table.cond, &jump_table.cases, jump_table.default_bb, &label, "t0", "t1");
+                continue;
+            }
+            // The rest of the chain is only reachable from its head, which is
+            // replaced by the jump table as a whole.
+            if env.is_in_jump_table(bb) {
+                continue;
+            }
             riscv_text.push_str(&format!("{}:\n", label));
             for &inst in node.insts().keys() {
                 inst.generate_riscv(riscv_text, env)?;
@@ -86,19 +150,53 @@ impl<'p> GenerateAsm<'p> for Value {
             ValueKind::Store(store) => {
                 generate_store(riscv_text, env, store.value(), store.dest(), "t0", "t1");
             }
-            ValueKind::Return(ret) => {
-                if let Some(ret_val) = ret.value() {
-                    generate_return(riscv_text, env, ret_val, "a0", "t0");
+            ValueKind::GetPtr(get_ptr) => {
+                let stride = get_pointee_size(env, get_ptr.src());
+                generate_get_ptr(riscv_text, env, get_ptr.src(), get_ptr.index(), stride, *self, "t0", "t1", "t2");
+            }
+            ValueKind::GetElemPtr(get_elem_ptr) => {
+                let stride = get_elem_size(env, get_elem_ptr.src());
+                generate_get_ptr(riscv_text, env, get_elem_ptr.src(), get_elem_ptr.index(), stride, *self, "t0", "t1", "t2");
+            }
+            ValueKind::Call(call) => {
+                let func_name = &env.get_program().func(call.callee()).name()[1..];
+                if env.is_func_variadic(call.callee()) {
+                    generate_variadic_call(riscv_text, env, func_name, call.args(), *self, "t0", "t1");
                 } else {
-                    return Err(CodegenError::MissingReturnValue);
+                    generate_func_call(riscv_text, env, func_name, call.args(), *self, "t0", "t1");
                 }
             }
+            ValueKind::Return(ret) => {
+                generate_return(riscv_text, env, ret.value(), "t0");
+            }
             _ => unimplemented!()
         }
         Ok(())
     }
 }
 
+impl<'p> GenerateAsm<'p> for ValueData {
+    type Out = ();
+
+    fn generate_riscv(&self, riscv_text: &mut String, env: &mut CodegenEnv<'p>) -> Result<Self::Out, CodegenError> {
+        match self.kind() {
+            ValueKind::Integer(i) => {
+                riscv_text.push_str(&format!("  .word {}\n", i.value()));
+            }
+            ValueKind::ZeroInit(_) => {
+                riscv_text.push_str(&format!("  .zero {}\n", self.ty().size()));
+            }
+            ValueKind::Aggregate(aggregate) => {
+                for &elem in aggregate.elems() {
+                    env.get_program().borrow_value(elem).generate_riscv(riscv_text, env)?;
+                }
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+}
+
 // impl<'p> GenerateAsm<'p> for ValueData {
 //     type Out = ();
 
@@ -2,29 +2,29 @@ mod asmutil;
 mod env;
 mod gen;
 
+use crate::irgen::Module;
 use env::CodegenEnv;
 use gen::GenerateAsm;
-use koopa::ir::Program;
+use koopa::ir::Type;
 use std::fmt;
 use std::result::Result;
 
-/// Generates the given Koopa IR program to RISC-V assembly.
-pub fn generate_riscv(program: &Program) -> Result<String, CodegenError> {
+/// Generates the given linked Koopa IR module to RISC-V assembly.
+pub fn generate_riscv(module: &Module) -> Result<String, CodegenError> {
+    Type::set_ptr_size(4);
     let mut riscv_text = String::new();
-    program.generate_riscv(&mut riscv_text, &mut CodegenEnv::new(program))?;
+    module.generate_riscv(&mut riscv_text, &mut CodegenEnv::new(module))?;
     Ok(riscv_text)
 }
 
 pub enum CodegenError {
     UnknownInstruction,
-    MissingReturnValue,
 }
 
 impl fmt::Display for CodegenError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
       match self {
         Self::UnknownInstruction => write!(f, "Unknown instruction"),
-        Self::MissingReturnValue => write!(f, "Missing return value"),
       }
     }
   }
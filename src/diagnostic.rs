@@ -0,0 +1,39 @@
+use crate::span::{ceil_char_boundary, floor_char_boundary, LineIndex, Span};
+
+/// A message attached to a span of the source file.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(span: Span, message: String) -> Self {
+        Self { span, message }
+    }
+
+    /// Renders the diagnostic as `file:line:col: message`, followed by the
+    /// source line and a caret under the span. A span that cuts a character
+    /// in half is widened to cover all of it.
+    pub fn render(&self, file: &str, source: &str, line_index: &LineIndex) -> String {
+        let start = floor_char_boundary(source, self.span.start);
+        let (line, col) = line_index.line_col(source, start);
+        let (line_start, line_end) = line_index.line_range(source, line);
+        let line_text = &source[line_start..line_end];
+        let start = start.min(line_end);
+        let end = ceil_char_boundary(source, self.span.end).clamp(start, line_end);
+        let width = source[start..end].chars().count().max(1);
+        let indent: String = line_text[..start - line_start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let gutter = " ".repeat(line.to_string().len());
+        format!(
+            "{}:{}:{}: {}\n{} |\n{} | {}\n{} | {}^{}",
+            file, line, col, self.message,
+            gutter,
+            line, line_text,
+            gutter, indent, "~".repeat(width - 1),
+        )
+    }
+}
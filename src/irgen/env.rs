@@ -1,62 +1,97 @@
+use std::collections::{HashMap, HashSet};
+
 use koopa::ir::builder::{BlockBuilder, LocalBuilder};
 use koopa::ir::dfg::DataFlowGraph;
 use koopa::ir::entities::Function;
 use koopa::ir::layout::{InstList, Layout};
-use koopa::ir::{BasicBlock, Program, Type, Value};
-
-use super::symbol::{SymbolInfo, SymbolTable};
+use koopa::ir::{BasicBlock, Program, Value};
 
-#[derive(Debug, Clone, Copy)]
-pub enum DeclType {
-    Global,
-    Local,
-}
+use super::JumpTable;
+use crate::sema::tree::{CompUnit, Func, FuncId, Object, ObjectId};
+use crate::sema::ty::Ty;
 
 pub struct IrgenEnv<'s> {
+    comp_unit: &'s CompUnit<'s>,
     cur_func: Option<Function>,
-    cur_func_type: Option<Type>,
     cur_bb: Option<BasicBlock>,
     cur_bb_returned: bool,
-    sym_tab: Vec<Box<SymbolTable<'s>>>,
+    objects: HashMap<ObjectId, Value>, // The allocations that hold the objects.
+    funcs: HashMap<FuncId, Function>,
+    cur_ret_ty: Option<&'s Ty>,
+    ret_alloc: Option<Value>, // Where a non-void function keeps its return value until the exit block.
     branch_id: i32,
     exit_bb: Option<BasicBlock>,
     and_id: i32,
     or_id: i32,
+    cond_id: i32,
     while_id: i32,
-    cur_while_cond_bb: Option<BasicBlock>,
-    cur_while_end_bb: Option<BasicBlock>,
-    cur_decl_type: Option<DeclType>,
+    for_id: i32,
+    do_while_id: i32,
+    switch_id: i32,
+    string_id: i32,
+    library_funcs: HashSet<Function>,
+    variadic_funcs: HashSet<Function>,
+    extern_globals: HashSet<Value>, // Placeholders for the globals defined in other translation units.
+    string_globals: HashSet<Value>, // The arrays of the string literals.
+    static_globals: HashSet<Value>, // Static variables, which get unique names.
+    jump_tables: HashMap<Function, Vec<JumpTable>>,
+    labels: HashMap<&'s str, BasicBlock>,
+    unreachable_id: i32,
+    static_id: i32,
+    cur_continue_bb: Option<BasicBlock>,
+    cur_break_bb: Option<BasicBlock>,
 }
 
 impl<'s> IrgenEnv<'s> {
-    pub fn new() -> Self {
-        Self { 
-            cur_func: None, 
-            cur_func_type: None,
-            cur_bb: None, 
-            cur_bb_returned: false, 
-            sym_tab: Vec::new(), 
-            branch_id: 0, 
+    pub fn new(comp_unit: &'s CompUnit<'s>) -> Self {
+        Self {
+            comp_unit,
+            cur_func: None,
+            cur_bb: None,
+            cur_bb_returned: false,
+            objects: HashMap::new(),
+            funcs: HashMap::new(),
+            cur_ret_ty: None,
+            ret_alloc: None,
+            branch_id: 0,
             exit_bb: None,
             and_id: 0,
             or_id: 0,
+            cond_id: 0,
             while_id: 0,
-            cur_while_cond_bb: None,
-            cur_while_end_bb: None,
-            cur_decl_type: None,
+            for_id: 0,
+            do_while_id: 0,
+            switch_id: 0,
+            string_id: 0,
+            library_funcs: HashSet::new(),
+            variadic_funcs: HashSet::new(),
+            extern_globals: HashSet::new(),
+            string_globals: HashSet::new(),
+            static_globals: HashSet::new(),
+            jump_tables: HashMap::new(),
+            labels: HashMap::new(),
+            unreachable_id: 0,
+            static_id: 0,
+            cur_continue_bb: None,
+            cur_break_bb: None,
         }
     }
 
-    pub fn set_cur_func(&mut self, func: Function) {
-        self.cur_func = Some(func);
+    pub fn get_object_info(&self, id: ObjectId) -> &'s Object<'s> {
+        self.comp_unit.object(id)
+    }
+
+    pub fn get_func_info(&self, id: FuncId) -> &'s Func<'s> {
+        self.comp_unit.func(id)
     }
 
-    pub fn get_cur_func_type(&self) -> Option<&Type> {
-        self.cur_func_type.as_ref()
+    // In words.
+    pub fn get_struct_size(&self, struct_id: usize) -> usize {
+        self.comp_unit.structs[struct_id].size
     }
 
-    pub fn set_cur_func_type(&mut self, ty: Type) {
-        self.cur_func_type = Some(ty);
+    pub fn set_cur_func(&mut self, func: Function) {
+        self.cur_func = Some(func);
     }
 
     pub fn set_cur_bb(&mut self, bb: BasicBlock) {
@@ -94,65 +129,88 @@ impl<'s> IrgenEnv<'s> {
         cur_func_data.layout_mut().bb_mut(cur_bb).insts_mut()
     }
 
-    pub fn push_scope(&mut self) {
-        self.sym_tab.push(Box::new(SymbolTable::new()));
+    pub fn set_object(&mut self, id: ObjectId, alloc: Value) {
+        self.objects.insert(id, alloc);
     }
 
-    pub fn pop_scope(&mut self) {
-        self.sym_tab.pop();
+    pub fn get_object(&self, id: ObjectId) -> Value {
+        self.objects[&id]
     }
 
-    pub fn new_symbol_const(&mut self, ident: &'s str, val: i32) {
-        let cur_sym_tab = self.sym_tab.last_mut().unwrap();
-        cur_sym_tab.set_value(ident, SymbolInfo::Const(val));
+    pub fn set_func(&mut self, id: FuncId, func: Function) {
+        self.funcs.insert(id, func);
     }
 
-    pub fn new_symbol_var(&mut self, ident: &'s str, val: Value) {
-        let cur_sym_tab = self.sym_tab.last_mut().unwrap();
-        cur_sym_tab.set_value(ident, SymbolInfo::Variable(val));
+    pub fn get_func(&self, id: FuncId) -> Option<Function> {
+        self.funcs.get(&id).copied()
     }
 
-    pub fn contains_symbol_in_cur_scope(&self, ident: &'s str) -> bool {
-        let cur_sym_tab = self.sym_tab.last().unwrap();
-        cur_sym_tab.contains_key(ident)
+    /// Marks `func` as a declaration of the runtime library, which needs no
+    /// definition in any translation unit.
+    pub fn set_func_library(&mut self, func: Function) {
+        self.library_funcs.insert(func);
     }
 
-    pub fn get_symbol(&self, ident: &'s str) -> Option<&SymbolInfo> {
-        for sym_tab in self.sym_tab.iter().rev() {
-            if let Some(symbol_info) = sym_tab.get_value(ident) {
-                return Some(symbol_info);
-            }
-        }
-        None
+    pub fn take_library_funcs(&mut self) -> HashSet<Function> {
+        std::mem::take(&mut self.library_funcs)
     }
 
-    pub fn containes_symbol_in_global_scope(&self, ident: &'s str) -> bool {
-        let global_sym_tab = self.sym_tab.first().unwrap();
-        global_sym_tab.contains_key(ident)
+    /// Marks `func` as taking its extra arguments in an array, which follows
+    /// its parameters together with the number of them.
+    pub fn set_func_variadic(&mut self, func: Function) {
+        self.variadic_funcs.insert(func);
     }
 
-    pub fn new_symbol_const_in_global_scope(&mut self, ident: &'s str, val: i32) {
-        let global_sym_tab = self.sym_tab.first_mut().unwrap();
-        global_sym_tab.set_value(ident, SymbolInfo::Const(val));
+    pub fn take_variadic_funcs(&mut self) -> HashSet<Function> {
+        std::mem::take(&mut self.variadic_funcs)
     }
 
-    pub fn new_symbol_var_in_global_scope(&mut self, ident: &'s str, val: Value) {
-        let global_sym_tab = self.sym_tab.first_mut().unwrap();
-        global_sym_tab.set_value(ident, SymbolInfo::Variable(val));
+    pub fn set_extern_global(&mut self, global_alloc: Value) {
+        self.extern_globals.insert(global_alloc);
     }
 
-    pub fn new_func(&mut self, ident: &'s str, func: Function) {
-        let global_sym_tab = self.sym_tab.first_mut().unwrap();
-        global_sym_tab.set_value(ident, SymbolInfo::Function(func));
+    pub fn take_extern_globals(&mut self) -> HashSet<Value> {
+        std::mem::take(&mut self.extern_globals)
     }
 
-    pub fn get_func(&self, ident: &'s str) -> Option<&Function> {
-        let global_sym_tab = self.sym_tab.first().unwrap();
-        if let Some(SymbolInfo::Function(func)) = global_sym_tab.get_value(ident) {
-            Some(func)
-        } else {
-            None
-        }
+    pub fn set_string_global(&mut self, global_alloc: Value) {
+        self.string_globals.insert(global_alloc);
+    }
+
+    pub fn take_string_globals(&mut self) -> HashSet<Value> {
+        std::mem::take(&mut self.string_globals)
+    }
+
+    pub fn set_static_global(&mut self, global_alloc: Value) {
+        self.static_globals.insert(global_alloc);
+    }
+
+    pub fn take_static_globals(&mut self) -> HashSet<Value> {
+        std::mem::take(&mut self.static_globals)
+    }
+
+    pub fn new_jump_table(&mut self, jump_table: JumpTable) {
+        self.jump_tables.entry(self.cur_func.unwrap()).or_default().push(jump_table);
+    }
+
+    pub fn take_jump_tables(&mut self) -> HashMap<Function, Vec<JumpTable>> {
+        std::mem::take(&mut self.jump_tables)
+    }
+
+    pub fn set_cur_ret_ty(&mut self, ty: &'s Ty) {
+        self.cur_ret_ty = Some(ty);
+    }
+
+    pub fn get_cur_ret_ty(&self) -> Option<&'s Ty> {
+        self.cur_ret_ty
+    }
+
+    pub fn set_ret_alloc(&mut self, alloc: Option<Value>) {
+        self.ret_alloc = alloc;
+    }
+
+    pub fn get_ret_alloc(&self) -> Option<Value> {
+        self.ret_alloc
     }
 
     pub fn set_cur_bb_returned(&mut self, returned: bool) {
@@ -181,41 +239,88 @@ impl<'s> IrgenEnv<'s> {
         or_id
     }
 
+    pub fn new_cond_id(&mut self) -> i32 {
+        let cond_id = self.cond_id;
+        self.cond_id += 1;
+        cond_id
+    }
+
     pub fn new_while_id(&mut self) -> i32 {
         let while_id = self.while_id;
         self.while_id += 1;
         while_id
     }
 
-    pub fn set_exit_bb(&mut self, bb: BasicBlock) {
-        self.exit_bb = Some(bb);
+    pub fn new_for_id(&mut self) -> i32 {
+        let for_id = self.for_id;
+        self.for_id += 1;
+        for_id
     }
 
-    pub fn get_exit_bb(&self) -> Option<&BasicBlock> {
-        self.exit_bb.as_ref()
+    pub fn new_do_while_id(&mut self) -> i32 {
+        let do_while_id = self.do_while_id;
+        self.do_while_id += 1;
+        do_while_id
+    }
+
+    pub fn new_switch_id(&mut self) -> i32 {
+        let switch_id = self.switch_id;
+        self.switch_id += 1;
+        switch_id
+    }
+
+    pub fn new_string_id(&mut self) -> i32 {
+        let string_id = self.string_id;
+        self.string_id += 1;
+        string_id
+    }
+
+    pub fn new_unreachable_id(&mut self) -> i32 {
+        let unreachable_id = self.unreachable_id;
+        self.unreachable_id += 1;
+        unreachable_id
+    }
+
+    pub fn new_static_id(&mut self) -> i32 {
+        let static_id = self.static_id;
+        self.static_id += 1;
+        static_id
+    }
+
+    pub fn get_label(&self, ident: &'s str) -> Option<BasicBlock> {
+        self.labels.get(ident).copied()
+    }
+
+    pub fn new_label(&mut self, ident: &'s str, bb: BasicBlock) {
+        self.labels.insert(ident, bb);
     }
 
-    pub fn set_cur_while_cond_bb(&mut self, bb: Option<BasicBlock>) {
-        self.cur_while_cond_bb = bb;
+    /// Forgets the labels of the current function.
+    pub fn clear_labels(&mut self) {
+        self.labels.clear();
     }
 
-    pub fn get_cur_while_cond_bb(&self) -> Option<BasicBlock> {
-        self.cur_while_cond_bb
+    pub fn set_exit_bb(&mut self, bb: BasicBlock) {
+        self.exit_bb = Some(bb);
+    }
+
+    pub fn get_exit_bb(&self) -> Option<&BasicBlock> {
+        self.exit_bb.as_ref()
     }
 
-    pub fn set_cur_while_end_bb(&mut self, bb: Option<BasicBlock>) {
-        self.cur_while_end_bb = bb;
+    pub fn set_cur_continue_bb(&mut self, bb: Option<BasicBlock>) {
+        self.cur_continue_bb = bb;
     }
 
-    pub fn get_cur_while_end_bb(&self) -> Option<BasicBlock> {
-        self.cur_while_end_bb
+    pub fn get_cur_continue_bb(&self) -> Option<BasicBlock> {
+        self.cur_continue_bb
     }
 
-    pub fn set_cur_decl_type(&mut self, decl_type: Option<DeclType>) {
-        self.cur_decl_type = decl_type;
+    pub fn set_cur_break_bb(&mut self, bb: Option<BasicBlock>) {
+        self.cur_break_bb = bb;
     }
 
-    pub fn get_cur_decl_type(&self) -> Option<DeclType> {
-        self.cur_decl_type
+    pub fn get_cur_break_bb(&self) -> Option<BasicBlock> {
+        self.cur_break_bb
     }
 }
@@ -1,341 +1,108 @@
-use super::{env::{DeclType, IrgenEnv}, eval::Evaluate, exp_type::ExpType, symbol::SymbolInfo, IrgenError};
-use crate::ast::*;
-use koopa::ir::{builder::{BasicBlockBuilder, GlobalInstBuilder, LocalInstBuilder, ValueBuilder}, BinaryOp, FunctionData, Program, Type, TypeKind};
-use std::result::Result;
+use super::{env::IrgenEnv, ty::ToKoopa, JumpTable};
+use crate::ast::{AssignOp, IncDecOp};
+use crate::sema::{label::ContainsLabel, tree, ty::Ty};
+use koopa::ir::{builder::{BasicBlockBuilder, GlobalInstBuilder, LocalInstBuilder, ValueBuilder}, BasicBlock, BinaryOp, FunctionData, Program, Type, Value, ValueKind};
 
 pub trait GenerateKoopa<'ast> {
     type Out;
 
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError>;
+    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Self::Out;
 }
 
-impl<'ast> GenerateKoopa<'ast> for CompUnit {
+impl<'ast> GenerateKoopa<'ast> for tree::CompUnit<'ast> {
     type Out = ();
 
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        env.push_scope();
-        let mut new_decl = |name: &'ast str , params_ty, ret_ty| {
-            let func = program.new_func(FunctionData::new_decl(
-                format!("@{}", name), 
-                params_ty, 
-                ret_ty
-            ));
-            env.new_func(name, func);
-        };
-        new_decl("getint", vec![], Type::get_i32());
-        new_decl("getch", vec![], Type::get_i32());
-        new_decl("getarray", vec![Type::get_pointer(Type::get_i32())], Type::get_i32());
-        new_decl("putint", vec![Type::get_i32()], Type::get_unit());
-        new_decl("putch", vec![Type::get_i32()], Type::get_unit());
-        new_decl("putarray", vec![Type::get_i32(), Type::get_pointer(Type::get_i32())], Type::get_unit());
-        new_decl("starttime", vec![], Type::get_unit());
-        new_decl("stoptime", vec![], Type::get_unit());
-
-        for comp_unit in &self.comp_unit_list {
-            match comp_unit {
-                SimpleCompUnit::Decl(decl) => {
-                    env.set_cur_decl_type(Some(DeclType::Global));
-                    decl.generate_koopa(program, env)?;
-                    env.set_cur_decl_type(None);
-                }
-                SimpleCompUnit::FuncDef(func_def) => {
-                    func_def.generate_koopa(program, env)?;
-                }
+    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Self::Out {
+        // The runtime library is declared before anything else. A function of
+        // it that this unit defines is no longer a part of it, and `putf` is
+        // only declared where it is called.
+        for (id, func_info) in self.funcs.iter().enumerate() {
+            if func_info.is_library && (func_info.is_called || !func_info.is_variadic) {
+                let func = generate_func_decl(tree::FuncId(id), false, program, env);
+                env.set_func_library(func);
             }
         }
-        env.pop_scope();
-        Ok(())
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for Decl {
-    type Out = ();
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        match self {
-            Self::ConstDecl(const_decl) => {
-                const_decl.generate_koopa(program, env)
-            },
-            Self::VarDecl(var_decl) => {
-                var_decl.generate_koopa(program, env)
-            },
-        }        
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for ConstDecl {
-    type Out = ();
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        for const_def in &self.const_def_list {
-            const_def.generate_koopa(program, env)?;
-        }
-        Ok(())
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for BType {
-    type Out = Type;
-
-    fn generate_koopa(&'ast self, _program: &mut Program, _env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        match self {
-            Self::Int => Ok(Type::get_i32()),
-            Self::Void => Ok(Type::get_unit()),
-        }
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for ConstDef {
-    type Out = ();
 
-    fn generate_koopa(&'ast self, _program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        let const_val = self.const_init_val.evaluate(env)?;
-        let decl_type = env.get_cur_decl_type().unwrap();
-        match decl_type {
-            DeclType::Global => {
-                if env.containes_symbol_in_global_scope(&self.ident) {
-                    return Err(IrgenError::SymbolDeclaredMoreThanOnce);
-                }
-                env.new_symbol_const_in_global_scope(&self.ident, const_val);
-            },
-            DeclType::Local => {
-                if env.contains_symbol_in_cur_scope(&self.ident) {
-                    return Err(IrgenError::SymbolDeclaredMoreThanOnce);
-                }
-                env.new_symbol_const(&self.ident, const_val);
-            }
-        }
-        Ok(())
-    }   
-}
-
-impl<'ast> GenerateKoopa<'ast> for VarDecl {
-    type Out = ();
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        for var_def in &self.var_def_list {
-            var_def.generate_koopa(program, env)?;
-        }
-        Ok(())
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for VarDef {
-    type Out = ();
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        let decl_type = env.get_cur_decl_type().unwrap();
-        match decl_type {
-            DeclType::Global => {
-                if env.containes_symbol_in_global_scope(&self.ident) {
-                    return Err(IrgenError::SymbolDeclaredMoreThanOnce);
-                }
-                let ty = self.b_type.generate_koopa(program, env)?;
-                let const_val = match self.init_val.as_ref() {
-                    Some(init_val) => {
-                        program.new_value().integer(init_val.evaluate(env)?)
-                    },
-                    None => {
-                        program.new_value().zero_init(ty)
+        // Global declarations and function signatures come first, so that a
+        // function can be called before its definition.
+        for item in &self.items {
+            match item {
+                tree::Item::Var(var_def) => {
+                    generate_global_var(var_def, program, env);
+                },
+                tree::Item::FuncDecl(func) => {
+                    // A prototype is only kept in the IR if the function isn't
+                    // defined, and only once.
+                    let func_info = env.get_func_info(*func);
+                    if !func_info.is_library && func_info.body.is_none() && env.get_func(*func).is_none() {
+                        generate_func_decl(*func, false, program, env);
                     }
-                };
-                let global_alloc = program.new_value().global_alloc(const_val);
-                program.set_value_name(global_alloc, Some(format!("@{}", self.ident)));
-                env.new_symbol_var_in_global_scope(&self.ident, global_alloc);
-            },
-            DeclType::Local => {
-                if env.contains_symbol_in_cur_scope(&self.ident) {
-                    return Err(IrgenError::SymbolDeclaredMoreThanOnce);
-                }
-                let ty = self.b_type.generate_koopa(program, env)?;
-                let alloc = env.new_value(program).alloc(ty);
-                env.dfg_mut(program).set_value_name(alloc, Some(format!("@{}", self.ident)));
-                env.new_inst(program).push_key_back(alloc).unwrap();
-                env.new_symbol_var(&self.ident, alloc);
-
-                if let Some(init_val) = self.init_val.as_ref() {
-                    let val = init_val.generate_koopa(program, env)?.to_int()?;
-                    let store = env.new_value(program).store(val, alloc);
-                    env.new_inst(program).push_key_back(store).unwrap();
-                }
-            }
-        }
-        Ok(())
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for InitVal {
-    type Out = ExpType;
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        self.exp.generate_koopa(program, env)
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for FuncDef {
-    type Out = ();
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        let mut params_ty = vec![];
-        if let Some(func_f_params) = self.func_f_params.as_ref() {
-            for func_f_param in &func_f_params.func_f_param_list {
-                let ty = func_f_param.b_type.generate_koopa(program, env)?;
-                params_ty.push(ty);
+                },
+                tree::Item::FuncDef(func) => {
+                    generate_func_decl(*func, true, program, env);
+                },
             }
         }
-        let ret_ty = self.func_type.generate_koopa(program, env)?;
-        let func = program.new_func(FunctionData::new(
-            format!("@{}", self.ident), 
-            params_ty.clone(), 
-            ret_ty.clone()
-        ));
-        env.new_func(&self.ident, func);
-        env.set_cur_func(func);
-        env.set_cur_func_type(ret_ty);
-
-        let entry = env.new_bb(program).basic_block(Some("%entry".into()));
-        let exit = env.new_bb(program).basic_block(Some("%exit".into()));
-        env.set_exit_bb(exit);
-
-        env.layout_mut(program).bbs_mut().extend([entry]);
-        env.set_cur_bb(entry);
-        env.set_cur_bb_returned(false);
-        env.push_scope();
-
-        match env.get_cur_func_type().unwrap().kind() {
-            TypeKind::Int32 => {
-                let alloc_ret = env.new_value(program).alloc(Type::get_i32());
-                env.new_inst(program).push_key_back(alloc_ret).unwrap();
-                env.dfg_mut(program).set_value_name(alloc_ret, Some("%ret".into()));
-                env.new_symbol_var("%ret", alloc_ret);
-            },
-            TypeKind::Unit => {},
-            _ => unreachable!()
-        }
-
-        if let Some(func_f_params) = self.func_f_params.as_ref() {
-            let params = program.func(func).params().to_vec();
-            for ((func_f_param, param_ty), param) in func_f_params.func_f_param_list.iter().zip(params_ty.iter()).zip(params.iter()) {
-                let alloc_param = env.new_value(program).alloc(param_ty.clone());
-                env.dfg_mut(program).set_value_name(alloc_param, Some(format!("@{}", func_f_param.ident)));
-                env.new_inst(program).push_key_back(alloc_param).unwrap();
-                env.new_symbol_var(&func_f_param.ident, alloc_param);
-                let store_param = env.new_value(program).store(*param, alloc_param);
-                env.new_inst(program).push_key_back(store_param).unwrap();
+        for item in &self.items {
+            if let tree::Item::FuncDef(func) = item {
+                generate_func_body(*func, program, env);
             }
         }
-
-        self.block.generate_koopa(program, env)?;
-
-        if !env.is_cur_bb_returned() {
-            let jump = env.new_value(program).jump(exit);
-            env.new_inst(program).push_key_back(jump).unwrap();
-        }
-
-        env.layout_mut(program).bbs_mut().extend([exit]);
-        env.set_cur_bb(exit);
-
-        match env.get_cur_func_type().unwrap().kind() {
-            TypeKind::Int32 => {
-                let alloc_ret = match env.get_symbol("%ret").unwrap() {
-                    SymbolInfo::Variable(alloc) => *alloc,
-                    _ => unreachable!()
-                };
-                let load = env.new_value(program).load(alloc_ret);
-                let ret = env.new_value(program).ret(Some(load));
-                env.new_inst(program).push_key_back(load).unwrap();
-                env.new_inst(program).push_key_back(ret).unwrap();
-            },
-            TypeKind::Unit => {
-                let ret = env.new_value(program).ret(None);
-                env.new_inst(program).push_key_back(ret).unwrap();
-            },
-            _ => unreachable!()
-        }
-
-        env.pop_scope();
-
-        Ok(())
     }
 }
 
-// impl<'ast> GenerateKoopa<'ast> for FuncType {
-//     type Out = Type;
-
-//     fn generate_koopa(&'ast self, _program: &mut Program, _env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-//         match self {
-//             Self::Int => Ok(Type::get_i32()),
-//             Self::Void => Ok(Type::get_unit()),
-//         }
-//     }
-// }
-
-impl<'ast> GenerateKoopa<'ast> for Block {
+impl<'ast> GenerateKoopa<'ast> for tree::Block<'ast> {
     type Out = ();
 
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        for block_item in &self.block_item_list {
-            if env.is_cur_bb_returned() {
-                break;
-            }
-            block_item.generate_koopa(program, env)?;
+    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Self::Out {
+        for (i, stmt) in self.stmt_list.iter().enumerate() {
+            let label_ahead = || self.stmt_list[i + 1..].iter().any(|stmt| stmt.contains_label());
+            generate_block_item(stmt, label_ahead, program, env);
         }
-        Ok(())
     }
 }
 
-impl<'ast> GenerateKoopa<'ast> for BlockItem {
+impl<'ast> GenerateKoopa<'ast> for tree::Stmt<'ast> {
     type Out = ();
 
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
+    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Self::Out {
         match self {
-            Self::Stmt(stmt) => {
-                stmt.generate_koopa(program, env)
+            Self::Decl(var_def_list) => {
+                for var_def in var_def_list {
+                    match env.get_object_info(var_def.object).storage {
+                        tree::Storage::Local => generate_local_var(var_def, program, env),
+                        _ => generate_global_var(var_def, program, env),
+                    }
+                }
             },
-            Self::Decl(decl) => {
-                env.set_cur_decl_type(Some(DeclType::Local));
-                let val = decl.generate_koopa(program, env)?;
-                env.set_cur_decl_type(None);
-                Ok(val)
+            Self::Assign(place, exp) => {
+                let val = exp.generate_koopa(program, env);
+                let ptr = place.generate_koopa(program, env);
+                let val = generate_convert(&place.ty, val, program, env);
+                let store = env.new_value(program).store(val, ptr);
+                env.new_inst(program).push_key_back(store).unwrap();
             },
-        }
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for Stmt {
-    type Out = ();
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        match self {
-            Self::OpenStmt(open_stmt) => {
-                open_stmt.generate_koopa(program, env)
+            Self::Exp(exp) => {
+                if let Some(exp) = exp.as_ref() {
+                    exp.generate_koopa(program, env);
+                }
             },
-            Self::ClosedStmt(closed_stmt) => {
-                closed_stmt.generate_koopa(program, env)
+            Self::Block(block) => {
+                block.generate_koopa(program, env);
             },
-        }
-    }
-}
-
-
-impl<'ast> GenerateKoopa<'ast> for OpenStmt {
-    type Out = ();
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        match self {
-            Self::If(exp, stmt) => {
+            Self::If(exp, then_stmt, None) => {
                 let bid = env.new_branch_id();
-                let then_bb = env.new_bb(program).basic_block(Some(format!("%then_{}", bid).into()));
-                let end_bb = env.new_bb(program).basic_block(Some(format!("%end_{}", bid).into()));
+                let then_bb = env.new_bb(program).basic_block(Some(format!("%then_{}", bid)));
+                let end_bb = env.new_bb(program).basic_block(Some(format!("%end_{}", bid)));
 
-                let cond = exp.generate_koopa(program, env)?.to_int()?;
+                let cond = exp.generate_koopa(program, env);
                 let br = env.new_value(program).branch(cond, then_bb, end_bb);
                 env.new_inst(program).push_key_back(br).unwrap();
 
                 env.layout_mut(program).bbs_mut().extend([then_bb]);
                 env.set_cur_bb(then_bb);
                 env.set_cur_bb_returned(false);
-                stmt.generate_koopa(program, env)?;
+                then_stmt.generate_koopa(program, env);
                 if !env.is_cur_bb_returned() {
                     let jump = env.new_value(program).jump(end_bb);
                     env.new_inst(program).push_key_back(jump).unwrap();
@@ -344,22 +111,21 @@ impl<'ast> GenerateKoopa<'ast> for OpenStmt {
                 env.layout_mut(program).bbs_mut().extend([end_bb]);
                 env.set_cur_bb(end_bb);
                 env.set_cur_bb_returned(false);
-                Ok(())
-            }
-            Self::IfElse(exp, then_stmt, else_stmt ) => {
+            },
+            Self::If(exp, then_stmt, Some(else_stmt)) => {
                 let bid = env.new_branch_id();
-                let then_bb = env.new_bb(program).basic_block(Some(format!("%then_{}", bid).into()));
-                let else_bb = env.new_bb(program).basic_block(Some(format!("%else_{}", bid).into()));
-                let end_bb = env.new_bb(program).basic_block(Some(format!("%end_{}", bid).into()));
+                let then_bb = env.new_bb(program).basic_block(Some(format!("%then_{}", bid)));
+                let else_bb = env.new_bb(program).basic_block(Some(format!("%else_{}", bid)));
+                let end_bb = env.new_bb(program).basic_block(Some(format!("%end_{}", bid)));
 
-                let cond = exp.generate_koopa(program, env)?.to_int()?;
+                let cond = exp.generate_koopa(program, env);
                 let br = env.new_value(program).branch(cond, then_bb, else_bb);
                 env.new_inst(program).push_key_back(br).unwrap();
-                
+
                 env.layout_mut(program).bbs_mut().extend([then_bb]);
                 env.set_cur_bb(then_bb);
                 env.set_cur_bb_returned(false);
-                then_stmt.generate_koopa(program, env)?;
+                then_stmt.generate_koopa(program, env);
                 if !env.is_cur_bb_returned() {
                     let jump = env.new_value(program).jump(end_bb);
                     env.new_inst(program).push_key_back(jump).unwrap();
@@ -368,7 +134,7 @@ impl<'ast> GenerateKoopa<'ast> for OpenStmt {
                 env.layout_mut(program).bbs_mut().extend([else_bb]);
                 env.set_cur_bb(else_bb);
                 env.set_cur_bb_returned(false);
-                else_stmt.generate_koopa(program, env)?;
+                else_stmt.generate_koopa(program, env);
                 if !env.is_cur_bb_returned() {
                     let jump = env.new_value(program).jump(end_bb);
                     env.new_inst(program).push_key_back(jump).unwrap();
@@ -377,32 +143,31 @@ impl<'ast> GenerateKoopa<'ast> for OpenStmt {
                 env.layout_mut(program).bbs_mut().extend([end_bb]);
                 env.set_cur_bb(end_bb);
                 env.set_cur_bb_returned(false);
-                Ok(())
-            }
+            },
             Self::While(exp, stmt) => {
-                let old_while_cond_bb = env.get_cur_while_cond_bb();
-                let old_while_end_bb = env.get_cur_while_end_bb();
+                let old_continue_bb = env.get_cur_continue_bb();
+                let old_break_bb = env.get_cur_break_bb();
 
                 let wid = env.new_while_id();
-                let cond_bb = env.new_bb(program).basic_block(Some(format!("%while_cond_{}", wid).into()));
-                let body_bb = env.new_bb(program).basic_block(Some(format!("%while_body_{}", wid).into()));
-                let end_bb = env.new_bb(program).basic_block(Some(format!("%while_end_{}", wid).into()));
+                let cond_bb = env.new_bb(program).basic_block(Some(format!("%while_cond_{}", wid)));
+                let body_bb = env.new_bb(program).basic_block(Some(format!("%while_body_{}", wid)));
+                let end_bb = env.new_bb(program).basic_block(Some(format!("%while_end_{}", wid)));
                 let jump = env.new_value(program).jump(cond_bb);
                 env.new_inst(program).push_key_back(jump).unwrap();
 
-                env.set_cur_while_cond_bb(Some(cond_bb));
-                env.set_cur_while_end_bb(Some(end_bb));
+                env.set_cur_continue_bb(Some(cond_bb));
+                env.set_cur_break_bb(Some(end_bb));
 
                 env.layout_mut(program).bbs_mut().extend([cond_bb]);
                 env.set_cur_bb(cond_bb);
-                let cond = exp.generate_koopa(program, env)?.to_int()?;
+                let cond = exp.generate_koopa(program, env);
                 let br = env.new_value(program).branch(cond, body_bb, end_bb);
                 env.new_inst(program).push_key_back(br).unwrap();
 
                 env.layout_mut(program).bbs_mut().extend([body_bb]);
                 env.set_cur_bb(body_bb);
                 env.set_cur_bb_returned(false);
-                stmt.generate_koopa(program, env)?;
+                stmt.generate_koopa(program, env);
                 if !env.is_cur_bb_returned() {
                     let jump = env.new_value(program).jump(cond_bb);
                     env.new_inst(program).push_key_back(jump).unwrap();
@@ -412,435 +177,242 @@ impl<'ast> GenerateKoopa<'ast> for OpenStmt {
                 env.set_cur_bb(end_bb);
                 env.set_cur_bb_returned(false);
 
-                env.set_cur_while_cond_bb(old_while_cond_bb);
-                env.set_cur_while_end_bb(old_while_end_bb);
-                Ok(())
-            }
-        }
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for ClosedStmt {
-    type Out = ();
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        match self {
-            Self::SimpleStmt(simple_stmt) => {
-                simple_stmt.generate_koopa(program, env)
+                env.set_cur_continue_bb(old_continue_bb);
+                env.set_cur_break_bb(old_break_bb);
             },
-            Self::IfElse(exp, then_stmt ,else_stmt ) => {
-                let bid = env.new_branch_id();
-                let then_bb = env.new_bb(program).basic_block(Some(format!("%then_{}", bid).into()));
-                let else_bb = env.new_bb(program).basic_block(Some(format!("%else_{}", bid).into()));
-                let end_bb = env.new_bb(program).basic_block(Some(format!("%end_{}", bid).into()));
+            Self::DoWhile(stmt, exp) => {
+                let old_continue_bb = env.get_cur_continue_bb();
+                let old_break_bb = env.get_cur_break_bb();
+
+                let did = env.new_do_while_id();
+                let body_bb = env.new_bb(program).basic_block(Some(format!("%do_body_{}", did)));
+                let cond_bb = env.new_bb(program).basic_block(Some(format!("%do_cond_{}", did)));
+                let end_bb = env.new_bb(program).basic_block(Some(format!("%do_end_{}", did)));
+                let jump = env.new_value(program).jump(body_bb);
+                env.new_inst(program).push_key_back(jump).unwrap();
 
-                let cond = exp.generate_koopa(program, env)?.to_int()?;
-                let br = env.new_value(program).branch(cond, then_bb, else_bb);
-                env.new_inst(program).push_key_back(br).unwrap();
-                
-                env.layout_mut(program).bbs_mut().extend([then_bb]);
-                env.set_cur_bb(then_bb);
-                env.set_cur_bb_returned(false);
-                then_stmt.generate_koopa(program, env)?;
-                if !env.is_cur_bb_returned() {
-                    let jump = env.new_value(program).jump(end_bb);
-                    env.new_inst(program).push_key_back(jump).unwrap();
-                }
+                env.set_cur_continue_bb(Some(cond_bb));
+                env.set_cur_break_bb(Some(end_bb));
 
-                env.layout_mut(program).bbs_mut().extend([else_bb]);
-                env.set_cur_bb(else_bb);
+                env.layout_mut(program).bbs_mut().extend([body_bb]);
+                env.set_cur_bb(body_bb);
                 env.set_cur_bb_returned(false);
-                else_stmt.generate_koopa(program, env)?;
+                stmt.generate_koopa(program, env);
                 if !env.is_cur_bb_returned() {
-                    let jump = env.new_value(program).jump(end_bb);
+                    let jump = env.new_value(program).jump(cond_bb);
                     env.new_inst(program).push_key_back(jump).unwrap();
                 }
 
-                env.layout_mut(program).bbs_mut().extend([end_bb]);
-                env.set_cur_bb(end_bb);
-                env.set_cur_bb_returned(false);
-                Ok(())
-            },
-            Self::While(exp, stmt) => {
-                let old_while_cond_bb = env.get_cur_while_cond_bb();
-                let old_while_end_bb = env.get_cur_while_end_bb();
-
-                let wid = env.new_while_id();
-                let cond_bb = env.new_bb(program).basic_block(Some(format!("%while_cond_{}", wid).into()));
-                let body_bb = env.new_bb(program).basic_block(Some(format!("%while_body_{}", wid).into()));
-                let end_bb = env.new_bb(program).basic_block(Some(format!("%while_end_{}", wid).into()));
-                let jump = env.new_value(program).jump(cond_bb);
-                env.new_inst(program).push_key_back(jump).unwrap();
-
-                env.set_cur_while_cond_bb(Some(cond_bb));
-                env.set_cur_while_end_bb(Some(end_bb));
-
                 env.layout_mut(program).bbs_mut().extend([cond_bb]);
                 env.set_cur_bb(cond_bb);
-                let cond = exp.generate_koopa(program, env)?.to_int()?;
+                env.set_cur_bb_returned(false);
+                let cond = exp.generate_koopa(program, env);
                 let br = env.new_value(program).branch(cond, body_bb, end_bb);
                 env.new_inst(program).push_key_back(br).unwrap();
 
-                env.layout_mut(program).bbs_mut().extend([body_bb]);
-                env.set_cur_bb(body_bb);
-                env.set_cur_bb_returned(false);
-                stmt.generate_koopa(program, env)?;
-                if !env.is_cur_bb_returned() {
-                    let jump = env.new_value(program).jump(cond_bb);
-                    env.new_inst(program).push_key_back(jump).unwrap();
-                }
-
                 env.layout_mut(program).bbs_mut().extend([end_bb]);
                 env.set_cur_bb(end_bb);
                 env.set_cur_bb_returned(false);
 
-                env.set_cur_while_cond_bb(old_while_cond_bb);
-                env.set_cur_while_end_bb(old_while_end_bb);
-                Ok(())
-            }
-        }
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for SimpleStmt {
-    type Out = ();
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        match self {
-            Self::Assign(l_val, exp) => {
-                let val = exp.generate_koopa(program, env)?.to_int()?;
-                if let Some(symbol_info) = env.get_symbol(&l_val.ident) {
-                    match symbol_info {
-                        SymbolInfo::Const(_) => {
-                            return Err(IrgenError::AssignToConst);
-                        },
-                        SymbolInfo::Variable(alloc) => {
-                            let store = env.new_value(program).store(val, *alloc);
-                            env.new_inst(program).push_key_back(store).unwrap();
-                        },
-                        SymbolInfo::Function(_) => {
-                            return Err(IrgenError::UseFunctionAsVariable);
-                        }
-                    }
-                } else {
-                    return Err(IrgenError::SymbolUndeclared);
-                }
+                env.set_cur_continue_bb(old_continue_bb);
+                env.set_cur_break_bb(old_break_bb);
             },
-            Self::Exp(exp) => {
-                if let Some(exp) = exp.as_ref() {
-                    exp.generate_koopa(program, env)?;
-                }
-            }
-            Self::Block(block) => {
-                env.push_scope();
-                block.generate_koopa(program, env)?;
-                env.pop_scope();
+            Self::For(for_init, exp, for_step, stmt) => {
+                generate_for(for_init.as_deref(), exp.as_ref(), for_step.as_deref(), stmt, program, env);
+            },
+            Self::Switch(exp, switch_item_list) => {
+                generate_switch(exp, switch_item_list, program, env);
             },
             Self::Break => {
-                if let Some(while_end_bb) = env.get_cur_while_end_bb() {
-                    let jump = env.new_value(program).jump(while_end_bb);
-                    env.new_inst(program).push_key_back(jump).unwrap();
-                    env.set_cur_bb_returned(true);
-                } else {
-                    return Err(IrgenError::BreakOutsideLoop);
-                }
+                let jump = env.new_value(program).jump(env.get_cur_break_bb().unwrap());
+                env.new_inst(program).push_key_back(jump).unwrap();
+                env.set_cur_bb_returned(true);
             },
             Self::Continue => {
-                if let Some(while_cond_bb) = env.get_cur_while_cond_bb() {
-                    let jump = env.new_value(program).jump(while_cond_bb);
-                    env.new_inst(program).push_key_back(jump).unwrap();
-                    env.set_cur_bb_returned(true);
-                } else {
-                    return Err(IrgenError::ContinueOutsideLoop);
-                }
+                let jump = env.new_value(program).jump(env.get_cur_continue_bb().unwrap());
+                env.new_inst(program).push_key_back(jump).unwrap();
+                env.set_cur_bb_returned(true);
+            },
+            Self::Goto(ident) => {
+                let bb = get_label_bb(ident, program, env);
+                let jump = env.new_value(program).jump(bb);
+                env.new_inst(program).push_key_back(jump).unwrap();
+                env.set_cur_bb_returned(true);
+            },
+            Self::Labeled(ident, stmt) => {
+                generate_label(ident, program, env);
+                stmt.generate_koopa(program, env);
             },
             Self::Return(exp) => {
-                match env.get_cur_func_type().unwrap().kind() {
-                    TypeKind::Int32 => {
-                        let ret_val = env.get_symbol("%ret").unwrap();
-                        let ret_val = match ret_val {
-                            SymbolInfo::Variable(alloc) => *alloc,
-                            _ => unreachable!()
-                        };
-                        match exp.as_ref() {
-                            Some(exp) => {
-                                let val = exp.generate_koopa(program, env)?.to_int()?;
-                                let store = env.new_value(program).store(val, ret_val);
-                                env.new_inst(program).push_key_back(store).unwrap();
-                            },
-                            None => {}
-                        }
-                        let jump = env.new_value(program).jump(*env.get_exit_bb().unwrap());
-                        env.new_inst(program).push_key_back(jump).unwrap();
-                        env.set_cur_bb_returned(true);
-                    },
-                    TypeKind::Unit => {
-                        if let Some(_) = exp.as_ref() {
-                            return Err(IrgenError::ReturnWithExpressionInVoidFunction);
-                        }
-                        let jump = env.new_value(program).jump(*env.get_exit_bb().unwrap());
-                        env.new_inst(program).push_key_back(jump).unwrap();
-                        env.set_cur_bb_returned(true);
-                    },
-                    _ => unreachable!()
+                if let Some(exp) = exp.as_ref() {
+                    let ret_alloc = env.get_ret_alloc().unwrap();
+                    let ret_ty = env.get_cur_ret_ty().unwrap();
+                    let val = exp.generate_koopa(program, env);
+                    let val = generate_convert(ret_ty, val, program, env);
+                    let store = env.new_value(program).store(val, ret_alloc);
+                    env.new_inst(program).push_key_back(store).unwrap();
                 }
+                let jump = env.new_value(program).jump(*env.get_exit_bb().unwrap());
+                env.new_inst(program).push_key_back(jump).unwrap();
+                env.set_cur_bb_returned(true);
             },
         }
-        
-        Ok(())
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for Exp {
-    type Out = ExpType;
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        self.l_or_exp.generate_koopa(program, env)
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for LVal {
-    type Out = ExpType;
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        if let Some(symbol_info) = env.get_symbol(&self.ident) {
-            match symbol_info {
-                SymbolInfo::Const(val) => {
-                    Ok(ExpType::Int(env.new_value(program).integer(*val)))
-                },
-                SymbolInfo::Variable(alloc) => {
-                    let load = env.new_value(program).load(*alloc);
-                    env.new_inst(program).push_key_back(load).unwrap();
-                    Ok(ExpType::Int(load))
-                },
-                SymbolInfo::Function(_) => {
-                    Err(IrgenError::UseFunctionAsVariable)
-                }
-            }
-        } else {
-            Err(IrgenError::SymbolUndeclared)
-        }
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for UnaryExp {
-    type Out = ExpType;
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        match self {
-            Self::PrimaryExp(primary_exp) => {
-                primary_exp.generate_koopa(program, env)
-            },
-            Self::FuncCall(ident, func_r_params) => {
-                let mut args = vec![];
-                if let Some(func_r_params) = func_r_params {
-                    for func_r_param in &func_r_params.exp_list {
-                        let arg = func_r_param.generate_koopa(program, env)?.to_int()?;
-                        args.push(arg);
-                    }
-                }
-                if let Some(func) = env.get_func(ident) {
-                    let call = env.new_value(program).call(*func, args);
-                    env.new_inst(program).push_key_back(call).unwrap();
-                    let func_ty = program.func(*func).ty();
-                    if let TypeKind::Function(_, ret_ty) = func_ty.kind() {
-                        match ret_ty.kind() {
-                            TypeKind::Int32 => {
-                                Ok(ExpType::Int(call))
-                            },
-                            TypeKind::Unit => {
-                                Ok(ExpType::Void)
-                            },
-                            _ => unreachable!(),
-                        }
-                    } else {
-                        unreachable!()
-                    }
-                } else {
-                    Err(IrgenError::FunctionUndeclared)
-                }
-            }
-            Self::UnaryExp(op, unary_exp) => {
-                let exp = unary_exp.generate_koopa(program, env)?.to_int()?;
-                let zero = env.new_value(program).integer(0);
-                let value = match op {
-                    UnaryOp::Plus => {
-                        return Ok(ExpType::Int(exp));
-                    },
-                    UnaryOp::Minus => {
-                        env.new_value(program).binary(BinaryOp::Sub, zero, exp)
-                    },
-                    UnaryOp::Not => {
-                        env.new_value(program).binary(BinaryOp::Eq, exp, zero)
-                    },
-                };
-                env.new_inst(program).push_key_back(value).unwrap();
-                Ok(ExpType::Int(value))
-            }
-        }
     }
 }
 
-impl<'ast> GenerateKoopa<'ast> for PrimaryExp {
-    type Out = ExpType;
+impl<'ast> GenerateKoopa<'ast> for tree::Place {
+    type Out = Value; // The address of the object.
 
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        match self {
-            Self::Exp(exp) => {
+    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Self::Out {
+        match &self.kind {
+            tree::PlaceKind::Object(object) => {
+                env.get_object(*object)
+            },
+            tree::PlaceKind::Index(base, index) => {
+                let ptr = base.generate_koopa(program, env);
+                let index = index.generate_koopa(program, env);
+                let elem_ptr = env.new_value(program).get_elem_ptr(ptr, index);
+                env.new_inst(program).push_key_back(elem_ptr).unwrap();
+                elem_ptr
+            },
+            tree::PlaceKind::PtrIndex(base, index) => {
+                let ptr = base.generate_koopa(program, env);
+                let load = env.new_value(program).load(ptr);
+                env.new_inst(program).push_key_back(load).unwrap();
+                let index = index.generate_koopa(program, env);
+                let elem_ptr = env.new_value(program).get_ptr(load, index);
+                env.new_inst(program).push_key_back(elem_ptr).unwrap();
+                elem_ptr
+            },
+            tree::PlaceKind::Deref(exp) => {
                 exp.generate_koopa(program, env)
             },
-            Self::LVal(l_val) => {
-                l_val.generate_koopa(program, env)
+            tree::PlaceKind::Field(base, steps) => {
+                generate_field_ptr(base, steps, program, env)
             },
-            Self::Number(num) => {
-                Ok(ExpType::Int(env.new_value(program).integer(*num)))
-            }
         }
     }
 }
 
-impl<'ast> GenerateKoopa<'ast> for MulExp {
-    type Out = ExpType;
+impl<'ast> GenerateKoopa<'ast> for tree::Expr {
+    type Out = Value;
 
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        match self {
-            Self::UnaryExp(unary_exp) => {
-                unary_exp.generate_koopa(program, env)
+    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Self::Out {
+        match &self.kind {
+            tree::ExprKind::Int(num) => {
+                env.new_value(program).integer(*num)
             },
-            Self::Mul(mul_exp, unary_exp) => {
-                let lhs = mul_exp.generate_koopa(program, env)?.to_int()?;
-                let rhs = unary_exp.generate_koopa(program, env)?.to_int()?;
-                let value = env.new_value(program).binary(BinaryOp::Mul, lhs, rhs);
-                env.new_inst(program).push_key_back(value).unwrap();
-                Ok(ExpType::Int(value))
+            tree::ExprKind::Load(place) => {
+                let ptr = place.generate_koopa(program, env);
+                let load = env.new_value(program).load(ptr);
+                env.new_inst(program).push_key_back(load).unwrap();
+                load
             },
-            Self::Div(mul_exp, unary_exp) => {
-                let lhs = mul_exp.generate_koopa(program, env)?.to_int()?;
-                let rhs = unary_exp.generate_koopa(program, env)?.to_int()?;
-                let value = env.new_value(program).binary(BinaryOp::Div, lhs, rhs);
-                env.new_inst(program).push_key_back(value).unwrap();
-                Ok(ExpType::Int(value))
+            tree::ExprKind::Decay(place) => {
+                let ptr = place.generate_koopa(program, env);
+                // A field is already addressed by its first word.
+                if let tree::PlaceKind::Field(..) = place.kind {
+                    return ptr;
+                }
+                let zero = env.new_value(program).integer(0);
+                let elem_ptr = env.new_value(program).get_elem_ptr(ptr, zero);
+                env.new_inst(program).push_key_back(elem_ptr).unwrap();
+                elem_ptr
             },
-            Self::Mod(mul_exp, unary_exp) => {
-                let lhs = mul_exp.generate_koopa(program, env)?.to_int()?;
-                let rhs = unary_exp.generate_koopa(program, env)?.to_int()?;
-                let value = env.new_value(program).binary(BinaryOp::Mod, lhs, rhs);
-                env.new_inst(program).push_key_back(value).unwrap();
-                Ok(ExpType::Int(value))
-            }
-        }
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for AddExp {
-    type Out = ExpType;
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        match self {
-            Self::MulExp(mul_exp) => {
-                mul_exp.generate_koopa(program, env)
+            tree::ExprKind::AddrOf(place) => {
+                place.generate_koopa(program, env)
             },
-            Self::Add(add_exp, mul_exp) => {
-                let lhs = add_exp.generate_koopa(program, env)?.to_int()?;
-                let rhs = mul_exp.generate_koopa(program, env)?.to_int()?;
-                let value = env.new_value(program).binary(BinaryOp::Add, lhs, rhs);
-                env.new_inst(program).push_key_back(value).unwrap();
-                Ok(ExpType::Int(value))
+            tree::ExprKind::String(string) => {
+                let words = string.iter().chain(&[0]).map(|&byte| byte as i32).collect();
+                generate_string(words, program, env)
             },
-            Self::Sub(add_exp, mul_exp) => {
-                let lhs = add_exp.generate_koopa(program, env)?.to_int()?;
-                let rhs = mul_exp.generate_koopa(program, env)?.to_int()?;
-                let value = env.new_value(program).binary(BinaryOp::Sub, lhs, rhs);
-                env.new_inst(program).push_key_back(value).unwrap();
-                Ok(ExpType::Int(value))
-            }
-        }
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for RelExp {
-    type Out = ExpType;
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        match self {
-            Self::AddExp(add_exp) => {
-                add_exp.generate_koopa(program, env)
+            tree::ExprKind::PackedString(string) => {
+                let mut bytes = string.clone();
+                bytes.push(0);
+                let words = bytes.chunks(4).map(|chunk| {
+                    let mut word = [0; 4];
+                    word[..chunk.len()].copy_from_slice(chunk);
+                    i32::from_le_bytes(word)
+                }).collect();
+                generate_string(words, program, env)
             },
-            Self::Lt(add_exp, rel_exp) => {
-                let lhs = add_exp.generate_koopa(program, env)?.to_int()?;
-                let rhs = rel_exp.generate_koopa(program, env)?.to_int()?;
-                let value = env.new_value(program).binary(BinaryOp::Lt, lhs, rhs);
-                env.new_inst(program).push_key_back(value).unwrap();
-                Ok(ExpType::Int(value))
+            tree::ExprKind::Call(func, args) => {
+                let func_info = env.get_func_info(*func);
+                let mut args: Vec<Value> = args.iter().map(|arg| arg.generate_koopa(program, env)).collect();
+                if func_info.is_variadic {
+                    let extra = args.split_off(func_info.params_ty.len());
+                    args.extend(generate_extra_args(extra, program, env));
+                }
+                let call = env.new_value(program).call(env.get_func(*func).unwrap(), args);
+                env.new_inst(program).push_key_back(call).unwrap();
+                call
             },
-            Self::Gt(add_exp, rel_exp) => {
-                let lhs = add_exp.generate_koopa(program, env)?.to_int()?;
-                let rhs = rel_exp.generate_koopa(program, env)?.to_int()?;
-                let value = env.new_value(program).binary(BinaryOp::Gt, lhs, rhs);
+            tree::ExprKind::Unary(op, exp) => {
+                let exp = exp.generate_koopa(program, env);
+                let zero = env.new_value(program).integer(0);
+                let value = match op {
+                    tree::UnaryOp::Minus => {
+                        env.new_value(program).binary(BinaryOp::Sub, zero, exp)
+                    },
+                    tree::UnaryOp::Not => {
+                        env.new_value(program).binary(BinaryOp::Eq, exp, zero)
+                    },
+                    tree::UnaryOp::BitNot => {
+                        let minus_one = env.new_value(program).integer(-1);
+                        env.new_value(program).binary(BinaryOp::Xor, exp, minus_one)
+                    },
+                };
                 env.new_inst(program).push_key_back(value).unwrap();
-                Ok(ExpType::Int(value))
+                value
             },
-            Self::Le(add_exp, rel_exp) => {
-                let lhs = add_exp.generate_koopa(program, env)?.to_int()?;
-                let rhs = rel_exp.generate_koopa(program, env)?.to_int()?;
-                let value = env.new_value(program).binary(BinaryOp::Le, lhs, rhs);
-                env.new_inst(program).push_key_back(value).unwrap();
-                Ok(ExpType::Int(value))
-            }
-            Self::Ge(add_exp, rel_exp) => {
-                let lhs = add_exp.generate_koopa(program, env)?.to_int()?;
-                let rhs = rel_exp.generate_koopa(program, env)?.to_int()?;
-                let value = env.new_value(program).binary(BinaryOp::Ge, lhs, rhs);
+            tree::ExprKind::Binary(op, lhs, rhs) => {
+                let lhs = lhs.generate_koopa(program, env);
+                let rhs = rhs.generate_koopa(program, env);
+                let op = match op {
+                    tree::BinaryOp::Mul => BinaryOp::Mul,
+                    tree::BinaryOp::Div => BinaryOp::Div,
+                    tree::BinaryOp::Mod => BinaryOp::Mod,
+                    tree::BinaryOp::Add => BinaryOp::Add,
+                    tree::BinaryOp::Sub => BinaryOp::Sub,
+                    tree::BinaryOp::Shl => BinaryOp::Shl,
+                    tree::BinaryOp::Sar => BinaryOp::Sar,
+                    tree::BinaryOp::Lt => BinaryOp::Lt,
+                    tree::BinaryOp::Gt => BinaryOp::Gt,
+                    tree::BinaryOp::Le => BinaryOp::Le,
+                    tree::BinaryOp::Ge => BinaryOp::Ge,
+                    tree::BinaryOp::Eq => BinaryOp::Eq,
+                    tree::BinaryOp::Ne => BinaryOp::NotEq,
+                    tree::BinaryOp::BitAnd => BinaryOp::And,
+                    tree::BinaryOp::BitXor => BinaryOp::Xor,
+                    tree::BinaryOp::BitOr => BinaryOp::Or,
+                };
+                let value = env.new_value(program).binary(op, lhs, rhs);
                 env.new_inst(program).push_key_back(value).unwrap();
-                Ok(ExpType::Int(value))
-            }
-        }  
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for EqExp {
-    type Out = ExpType;
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        match self {
-            Self::RelExp(rel_exp) => {
-                rel_exp.generate_koopa(program, env)
+                value
             },
-            Self::Eq(eq_exp, rel_exp) => {
-                let lhs = eq_exp.generate_koopa(program, env)?.to_int()?;
-                let rhs = rel_exp.generate_koopa(program, env)?.to_int()?;
-                let value = env.new_value(program).binary(BinaryOp::Eq, lhs, rhs);
-                env.new_inst(program).push_key_back(value).unwrap();
-                Ok(ExpType::Int(value))
+            tree::ExprKind::PtrAdd(lhs, rhs) => {
+                let lhs_val = lhs.generate_koopa(program, env);
+                let rhs_val = rhs.generate_koopa(program, env);
+                let (ptr, index) = match lhs.ty {
+                    Ty::Pointer(_) => (lhs_val, rhs_val),
+                    _ => (rhs_val, lhs_val),
+                };
+                generate_pointer_arith(ptr, BinaryOp::Add, index, program, env)
             },
-            Self::Ne(eq_exp, rel_exp) => {
-                let lhs = eq_exp.generate_koopa(program, env)?.to_int()?;
-                let rhs = rel_exp.generate_koopa(program, env)?.to_int()?;
-                let value = env.new_value(program).binary(BinaryOp::NotEq, lhs, rhs);
-                env.new_inst(program).push_key_back(value).unwrap();
-                Ok(ExpType::Int(value))
-            }
-        }
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for LAndExp {
-    type Out = ExpType;
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        match self {
-            Self::EqExp(eq_exp) => {
-                eq_exp.generate_koopa(program, env)
+            tree::ExprKind::PtrSub(lhs, rhs) => {
+                let ptr = lhs.generate_koopa(program, env);
+                let index = rhs.generate_koopa(program, env);
+                generate_pointer_arith(ptr, BinaryOp::Sub, index, program, env)
             },
-            Self::And(l_and_exp, eq_exp) => {
+            tree::ExprKind::And(lhs, rhs) => {
                 let aid = env.new_and_id();
-                let rhs_bb = env.new_bb(program).basic_block(Some(format!("%and_rhs_{}", aid).into()));
-                let end_bb = env.new_bb(program).basic_block(Some(format!("%and_end_{}", aid).into()));
+                let rhs_bb = env.new_bb(program).basic_block(Some(format!("%and_rhs_{}", aid)));
+                let end_bb = env.new_bb(program).basic_block(Some(format!("%and_end_{}", aid)));
 
                 let alloc_res = env.new_value(program).alloc(Type::get_i32());
                 env.new_inst(program).push_key_back(alloc_res).unwrap();
 
-                let lhs = l_and_exp.generate_koopa(program, env)?.to_int()?;
+                let lhs = lhs.generate_koopa(program, env);
                 let zero = env.new_value(program).integer(0);
                 let lhs_ne_zero = env.new_value(program).binary(BinaryOp::NotEq, lhs, zero);
                 env.new_inst(program).push_key_back(lhs_ne_zero).unwrap();
@@ -851,7 +423,7 @@ impl<'ast> GenerateKoopa<'ast> for LAndExp {
 
                 env.layout_mut(program).bbs_mut().extend([rhs_bb]);
                 env.set_cur_bb(rhs_bb);
-                let rhs = eq_exp.generate_koopa(program, env)?.to_int()?;
+                let rhs = rhs.generate_koopa(program, env);
                 let zero = env.new_value(program).integer(0);
                 let rhs_ne_zero = env.new_value(program).binary(BinaryOp::NotEq, rhs, zero);
                 env.new_inst(program).push_key_back(rhs_ne_zero).unwrap();
@@ -864,29 +436,17 @@ impl<'ast> GenerateKoopa<'ast> for LAndExp {
                 env.set_cur_bb(end_bb);
                 let load = env.new_value(program).load(alloc_res);
                 env.new_inst(program).push_key_back(load).unwrap();
-                Ok(ExpType::Int(load))
-            }
-        }
-    }
-}
-
-impl<'ast> GenerateKoopa<'ast> for LOrExp {
-    type Out = ExpType;
-
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        match self {
-            Self::LAndExp(l_and_exp) => {
-                Ok(l_and_exp.generate_koopa(program, env)?)
-            }
-            Self::Or(l_or_exp, l_and_exp) => {
+                load
+            },
+            tree::ExprKind::Or(lhs, rhs) => {
                 let oid = env.new_or_id();
-                let rhs_bb = env.new_bb(program).basic_block(Some(format!("%or_rhs_{}", oid).into()));
-                let end_bb = env.new_bb(program).basic_block(Some(format!("%or_end_{}", oid).into()));
-                
+                let rhs_bb = env.new_bb(program).basic_block(Some(format!("%or_rhs_{}", oid)));
+                let end_bb = env.new_bb(program).basic_block(Some(format!("%or_end_{}", oid)));
+
                 let alloc_res = env.new_value(program).alloc(Type::get_i32());
                 env.new_inst(program).push_key_back(alloc_res).unwrap();
 
-                let lhs = l_or_exp.generate_koopa(program, env)?.to_int()?;
+                let lhs = lhs.generate_koopa(program, env);
                 let zero = env.new_value(program).integer(0);
                 let lhs_ne_zero = env.new_value(program).binary(BinaryOp::NotEq, lhs, zero);
                 env.new_inst(program).push_key_back(lhs_ne_zero).unwrap();
@@ -897,7 +457,7 @@ impl<'ast> GenerateKoopa<'ast> for LOrExp {
 
                 env.layout_mut(program).bbs_mut().extend([rhs_bb]);
                 env.set_cur_bb(rhs_bb);
-                let rhs = l_and_exp.generate_koopa(program, env)?.to_int()?;
+                let rhs = rhs.generate_koopa(program, env);
                 let zero = env.new_value(program).integer(0);
                 let rhs_ne_zero = env.new_value(program).binary(BinaryOp::NotEq, rhs, zero);
                 env.new_inst(program).push_key_back(rhs_ne_zero).unwrap();
@@ -910,16 +470,648 @@ impl<'ast> GenerateKoopa<'ast> for LOrExp {
                 env.set_cur_bb(end_bb);
                 let load = env.new_value(program).load(alloc_res);
                 env.new_inst(program).push_key_back(load).unwrap();
-                Ok(ExpType::Int(load))
+                load
+            },
+            tree::ExprKind::Cond(cond, true_exp, false_exp) => {
+                let cid = env.new_cond_id();
+                let true_bb = env.new_bb(program).basic_block(Some(format!("%cond_true_{}", cid)));
+                let false_bb = env.new_bb(program).basic_block(Some(format!("%cond_false_{}", cid)));
+                let end_bb = env.new_bb(program).basic_block(Some(format!("%cond_end_{}", cid)));
+
+                let alloc_res = env.new_value(program).alloc(Type::get_i32());
+                env.new_inst(program).push_key_back(alloc_res).unwrap();
+
+                let cond = cond.generate_koopa(program, env);
+                let br = env.new_value(program).branch(cond, true_bb, false_bb);
+                env.new_inst(program).push_key_back(br).unwrap();
+
+                env.layout_mut(program).bbs_mut().extend([true_bb]);
+                env.set_cur_bb(true_bb);
+                let val = true_exp.generate_koopa(program, env);
+                let store = env.new_value(program).store(val, alloc_res);
+                env.new_inst(program).push_key_back(store).unwrap();
+                let jump = env.new_value(program).jump(end_bb);
+                env.new_inst(program).push_key_back(jump).unwrap();
+
+                env.layout_mut(program).bbs_mut().extend([false_bb]);
+                env.set_cur_bb(false_bb);
+                let val = false_exp.generate_koopa(program, env);
+                let store = env.new_value(program).store(val, alloc_res);
+                env.new_inst(program).push_key_back(store).unwrap();
+                let jump = env.new_value(program).jump(end_bb);
+                env.new_inst(program).push_key_back(jump).unwrap();
+
+                env.layout_mut(program).bbs_mut().extend([end_bb]);
+                env.set_cur_bb(end_bb);
+                let load = env.new_value(program).load(alloc_res);
+                env.new_inst(program).push_key_back(load).unwrap();
+                load
+            },
+            tree::ExprKind::CompoundAssign(place, assign_op, exp) => {
+                let ptr = place.generate_koopa(program, env);
+                let rhs = exp.generate_koopa(program, env);
+                let lhs = env.new_value(program).load(ptr);
+                env.new_inst(program).push_key_back(lhs).unwrap();
+                let op = match assign_op {
+                    AssignOp::Add => BinaryOp::Add,
+                    AssignOp::Sub => BinaryOp::Sub,
+                    AssignOp::Mul => BinaryOp::Mul,
+                    AssignOp::Div => BinaryOp::Div,
+                    AssignOp::Mod => BinaryOp::Mod,
+                };
+                let value = if let Ty::Pointer(_) = place.ty {
+                    generate_pointer_arith(lhs, op, rhs, program, env)
+                } else {
+                    let value = env.new_value(program).binary(op, lhs, rhs);
+                    env.new_inst(program).push_key_back(value).unwrap();
+                    generate_convert(&place.ty, value, program, env)
+                };
+                let store = env.new_value(program).store(value, ptr);
+                env.new_inst(program).push_key_back(store).unwrap();
+                value
+            },
+            tree::ExprKind::PrefixIncDec(op, place) => {
+                let (_, new_value) = generate_inc_dec(*op, place, program, env);
+                new_value
+            },
+            tree::ExprKind::PostfixIncDec(place, op) => {
+                let (old_value, _) = generate_inc_dec(*op, place, program, env);
+                old_value
+            },
+        }
+    }
+}
+
+/// Declares or defines a function. The IR has no variadic functions, so the
+/// extra arguments of one are passed in an array, and it takes a pointer to it
+/// and its length after its parameters. This is internal to the compiler:
+/// codegen passes the extra arguments one by one, as the runtime library
+/// expects.
+fn generate_func_decl<'ast>(func: tree::FuncId, is_def: bool, program: &mut Program, env: &mut IrgenEnv<'ast>) -> koopa::ir::Function {
+    let func_info = env.get_func_info(func);
+    let mut params_ty: Vec<Type> = func_info.params_ty.iter().map(|ty| ty.to_koopa(env)).collect();
+    if func_info.is_variadic {
+        params_ty.extend([Type::get_pointer(Type::get_i32()), Type::get_i32()]);
+    }
+    let ret_ty = func_info.ret_ty.to_koopa(env);
+    let func_data = if is_def {
+        FunctionData::new(format!("@{}", func_info.ident), params_ty, ret_ty)
+    } else {
+        FunctionData::new_decl(format!("@{}", func_info.ident), params_ty, ret_ty)
+    };
+    let koopa_func = program.new_func(func_data);
+    if func_info.is_variadic {
+        env.set_func_variadic(koopa_func);
+    }
+    env.set_func(func, koopa_func);
+    koopa_func
+}
+
+/// Puts the words of a string literal into a private global array, and
+/// returns a pointer to the first of them.
+fn generate_string<'ast>(words: Vec<i32>, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Value {
+    let elems = words.into_iter().map(|word| program.new_value().integer(word)).collect();
+    let init = program.new_value().aggregate(elems);
+    let string_alloc = program.new_value().global_alloc(init);
+    program.set_value_name(string_alloc, Some(format!("@_sysy_str_{}", env.new_string_id())));
+    env.set_string_global(string_alloc);
+    let zero = env.new_value(program).integer(0);
+    let ptr = env.new_value(program).get_elem_ptr(string_alloc, zero);
+    env.new_inst(program).push_key_back(ptr).unwrap();
+    ptr
+}
+
+/// Stores the extra arguments of a call to a variadic function into an array,
+/// and returns a pointer to it and its length. Without any, the pointer is
+/// never read.
+fn generate_extra_args<'ast>(extra: Vec<Value>, program: &mut Program, env: &mut IrgenEnv<'ast>) -> [Value; 2] {
+    let len = env.new_value(program).integer(extra.len() as i32);
+    if extra.is_empty() {
+        let ptr = env.new_value(program).undef(Type::get_pointer(Type::get_i32()));
+        return [ptr, len];
+    }
+    let array = env.new_value(program).alloc(Type::get_array(Type::get_i32(), extra.len()));
+    env.new_inst(program).push_key_back(array).unwrap();
+    for (i, arg) in extra.into_iter().enumerate() {
+        let index = env.new_value(program).integer(i as i32);
+        let elem = env.new_value(program).get_elem_ptr(array, index);
+        let store = env.new_value(program).store(arg, elem);
+        env.new_inst(program).push_key_back(elem).unwrap();
+        env.new_inst(program).push_key_back(store).unwrap();
+    }
+    let zero = env.new_value(program).integer(0);
+    let ptr = env.new_value(program).get_elem_ptr(array, zero);
+    env.new_inst(program).push_key_back(ptr).unwrap();
+    [ptr, len]
+}
+
+fn generate_func_body<'ast>(func: tree::FuncId, program: &mut Program, env: &mut IrgenEnv<'ast>) {
+    let func_info = env.get_func_info(func);
+    let body = func_info.body.as_ref().unwrap();
+    let koopa_func = env.get_func(func).unwrap();
+    env.set_cur_func(koopa_func);
+    env.set_cur_ret_ty(&func_info.ret_ty);
+
+    let entry = env.new_bb(program).basic_block(Some("%entry".into()));
+    let exit = env.new_bb(program).basic_block(Some("%exit".into()));
+    env.set_exit_bb(exit);
+
+    env.layout_mut(program).bbs_mut().extend([entry]);
+    env.set_cur_bb(entry);
+    env.set_cur_bb_returned(false);
+
+    let ret_alloc = if func_info.ret_ty != Ty::Void {
+        let alloc_ret = env.new_value(program).alloc(func_info.ret_ty.to_koopa(env));
+        env.new_inst(program).push_key_back(alloc_ret).unwrap();
+        env.dfg_mut(program).set_value_name(alloc_ret, Some("%ret".into()));
+        Some(alloc_ret)
+    } else {
+        None
+    };
+    env.set_ret_alloc(ret_alloc);
+
+    let params = program.func(koopa_func).params().to_vec();
+    for (object, param) in body.params.iter().zip(params) {
+        let Some(object) = *object else {
+            continue;
+        };
+        let object_info = env.get_object_info(object);
+        let alloc_param = env.new_value(program).alloc(object_info.ty.to_koopa(env));
+        env.dfg_mut(program).set_value_name(alloc_param, Some(format!("@{}", object_info.ident)));
+        env.new_inst(program).push_key_back(alloc_param).unwrap();
+        env.set_object(object, alloc_param);
+        let param = generate_convert(&object_info.ty, param, program, env);
+        let store_param = env.new_value(program).store(param, alloc_param);
+        env.new_inst(program).push_key_back(store_param).unwrap();
+    }
+
+    body.block.generate_koopa(program, env);
+
+    if !env.is_cur_bb_returned() {
+        let jump = env.new_value(program).jump(exit);
+        env.new_inst(program).push_key_back(jump).unwrap();
+    }
+
+    env.layout_mut(program).bbs_mut().extend([exit]);
+    env.set_cur_bb(exit);
+
+    match ret_alloc {
+        None => {
+            let ret = env.new_value(program).ret(None);
+            env.new_inst(program).push_key_back(ret).unwrap();
+        },
+        Some(alloc_ret) => {
+            let load = env.new_value(program).load(alloc_ret);
+            let ret = env.new_value(program).ret(Some(load));
+            env.new_inst(program).push_key_back(load).unwrap();
+            env.new_inst(program).push_key_back(ret).unwrap();
+        },
+    }
+
+    env.clear_labels();
+}
+
+/// Generates an initializer of a local object of type `ty`. A constant has
+/// already been converted by the semantic analysis.
+fn generate_local_init<'ast>(ty: &Ty, exp: &'ast tree::Expr, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Value {
+    let val = exp.generate_koopa(program, env);
+    match exp.kind {
+        tree::ExprKind::Int(_) => val,
+        _ => generate_convert(ty, val, program, env),
+    }
+}
+
+/// Converts `val` to `ty` for storing it. A `char` is an unsigned byte, as in
+/// the RISC-V ABI, and a constant is narrowed at compile time.
+fn generate_convert<'ast>(ty: &Ty, val: Value, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Value {
+    match ty {
+        Ty::Char => {
+            if let ValueKind::Integer(int) = env.dfg_mut(program).value(val).kind() {
+                let narrowed = int.value() as u8 as i32;
+                return env.new_value(program).integer(narrowed);
+            }
+            let mask = env.new_value(program).integer(0xff);
+            let value = env.new_value(program).binary(BinaryOp::And, val, mask);
+            env.new_inst(program).push_key_back(value).unwrap();
+            value
+        },
+        _ => val,
+    }
+}
+
+/// Generates a global, an extern or a static variable. Static variables get
+/// unique names, since those in different scopes may share a name.
+fn generate_global_var<'ast>(var_def: &'ast tree::VarDef, program: &mut Program, env: &mut IrgenEnv<'ast>) {
+    let object_info = env.get_object_info(var_def.object);
+    let init = match &var_def.init {
+        tree::Init::Const(vals) => match object_info.ty.dims() {
+            (dims, _) if dims.is_empty() => program.new_value().integer(vals[0]),
+            (dims, _) => generate_global_aggregate(program, &dims, vals),
+        },
+        _ => program.new_value().zero_init(object_info.ty.to_koopa(env)),
+    };
+    let global_alloc = program.new_value().global_alloc(init);
+    let name = match object_info.storage {
+        tree::Storage::Static => format!("@_sysy_static_{}_{}", object_info.ident, env.new_static_id()),
+        _ => format!("@{}", object_info.ident),
+    };
+    program.set_value_name(global_alloc, Some(name));
+    // The IR can't declare a global without defining it, so an extern one is
+    // a placeholder that the linker replaces with the definition.
+    match object_info.storage {
+        tree::Storage::Extern => env.set_extern_global(global_alloc),
+        tree::Storage::Static => env.set_static_global(global_alloc),
+        _ => {},
+    }
+    env.set_object(var_def.object, global_alloc);
+}
+
+fn generate_local_var<'ast>(var_def: &'ast tree::VarDef, program: &mut Program, env: &mut IrgenEnv<'ast>) {
+    let object_info = env.get_object_info(var_def.object);
+    let alloc = env.new_value(program).alloc(object_info.ty.to_koopa(env));
+    env.dfg_mut(program).set_value_name(alloc, Some(format!("@{}", object_info.ident)));
+    env.new_inst(program).push_key_back(alloc).unwrap();
+    env.set_object(var_def.object, alloc);
+
+    let (dims, elem_ty) = object_info.ty.dims();
+    match &var_def.init {
+        tree::Init::None => {},
+        tree::Init::Exp(exp) => {
+            let val = generate_local_init(&object_info.ty, exp, program, env);
+            let store = env.new_value(program).store(val, alloc);
+            env.new_inst(program).push_key_back(store).unwrap();
+        },
+        tree::Init::Const(vals) => {
+            let vals = vals.iter().map(|&val| env.new_value(program).integer(val)).collect();
+            generate_local_array_init(alloc, &dims, vals, program, env);
+        },
+        tree::Init::List(elems) => {
+            let mut vals = vec![];
+            for elem in elems {
+                vals.push(match elem {
+                    Some(exp) => generate_local_init(elem_ty, exp, program, env),
+                    None => env.new_value(program).integer(0),
+                });
+            }
+            generate_local_array_init(alloc, &dims, vals, program, env);
+        },
+    }
+}
+
+fn generate_global_aggregate(program: &mut Program, dims: &[usize], vals: &[i32]) -> Value {
+    let elems = if dims.len() == 1 {
+        vals.iter().map(|&val| program.new_value().integer(val)).collect()
+    } else {
+        let stride = dims[1..].iter().product();
+        vals.chunks(stride).map(|chunk| generate_global_aggregate(program, &dims[1..], chunk)).collect()
+    };
+    program.new_value().aggregate(elems)
+}
+
+/// Stores `vals` into the local array `alloc` element by element in row-major order.
+fn generate_local_array_init<'ast>(alloc: Value, dims: &[usize], vals: Vec<Value>, program: &mut Program, env: &mut IrgenEnv<'ast>) {
+    for (pos, val) in vals.into_iter().enumerate() {
+        let mut ptr = alloc;
+        let mut rem = pos;
+        for k in 0..dims.len() {
+            let stride: usize = dims[k + 1..].iter().product();
+            let index = env.new_value(program).integer((rem / stride) as i32);
+            rem %= stride;
+            ptr = env.new_value(program).get_elem_ptr(ptr, index);
+            env.new_inst(program).push_key_back(ptr).unwrap();
+        }
+        let store = env.new_value(program).store(val, ptr);
+        env.new_inst(program).push_key_back(store).unwrap();
+    }
+}
+
+/// Moves `ptr` by `index` elements, backwards for a subtraction.
+fn generate_pointer_arith<'ast>(ptr: Value, op: BinaryOp, index: Value, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Value {
+    let index = match op {
+        BinaryOp::Sub => {
+            let zero = env.new_value(program).integer(0);
+            let neg = env.new_value(program).binary(BinaryOp::Sub, zero, index);
+            env.new_inst(program).push_key_back(neg).unwrap();
+            neg
+        },
+        _ => index,
+    };
+    let value = env.new_value(program).get_ptr(ptr, index);
+    env.new_inst(program).push_key_back(value).unwrap();
+    value
+}
+
+/// Generates the address of a field, as an `*i32` pointing at its first word.
+fn generate_field_ptr<'ast>(base: &'ast tree::Place, steps: &'ast [tree::FieldStep], program: &mut Program, env: &mut IrgenEnv<'ast>) -> Value {
+    let base = base.generate_koopa(program, env);
+    let zero = env.new_value(program).integer(0);
+    let mut ptr = env.new_value(program).get_elem_ptr(base, zero);
+    env.new_inst(program).push_key_back(ptr).unwrap();
+
+    for step in steps {
+        if step.offset != 0 {
+            let offset = env.new_value(program).integer(step.offset as i32);
+            ptr = env.new_value(program).get_ptr(ptr, offset);
+            env.new_inst(program).push_key_back(ptr).unwrap();
+        }
+        for (index, stride) in &step.index_list {
+            let mut index = index.generate_koopa(program, env);
+            if *stride != 1 {
+                let stride = env.new_value(program).integer(*stride as i32);
+                index = env.new_value(program).binary(BinaryOp::Mul, index, stride);
+                env.new_inst(program).push_key_back(index).unwrap();
             }
+            ptr = env.new_value(program).get_ptr(ptr, index);
+            env.new_inst(program).push_key_back(ptr).unwrap();
         }
     }
+    ptr
 }
 
-impl<'ast> GenerateKoopa<'ast> for ConstExp {
-    type Out = ExpType;
+/// Returns the values of the place before and after the update. A pointer
+/// moves by one element.
+fn generate_inc_dec<'ast>(op: IncDecOp, place: &'ast tree::Place, program: &mut Program, env: &mut IrgenEnv<'ast>) -> (Value, Value) {
+    let ptr = place.generate_koopa(program, env);
+    let old_value = env.new_value(program).load(ptr);
+    env.new_inst(program).push_key_back(old_value).unwrap();
+    let one = env.new_value(program).integer(1);
+    let op = match op {
+        IncDecOp::Inc => BinaryOp::Add,
+        IncDecOp::Dec => BinaryOp::Sub,
+    };
+    let new_value = if let Ty::Pointer(_) = place.ty {
+        generate_pointer_arith(old_value, op, one, program, env)
+    } else {
+        let new_value = env.new_value(program).binary(op, old_value, one);
+        env.new_inst(program).push_key_back(new_value).unwrap();
+        generate_convert(&place.ty, new_value, program, env)
+    };
+    let store = env.new_value(program).store(new_value, ptr);
+    env.new_inst(program).push_key_back(store).unwrap();
+    (old_value, new_value)
+}
 
-    fn generate_koopa(&'ast self, program: &mut Program, env: &mut IrgenEnv<'ast>) -> Result<Self::Out, IrgenError> {
-        self.exp.generate_koopa(program, env)
+/// Generates a statement of a block, which may follow a jump. Such code is
+/// unreachable and skipped, unless it contains a label that a `goto` may jump
+/// to, or it declares what the code after a later label may use.
+fn generate_block_item<'ast, F>(stmt: &'ast tree::Stmt<'ast>, label_ahead: F, program: &mut Program, env: &mut IrgenEnv<'ast>)
+where
+    F: FnOnce() -> bool,
+{
+    if env.is_cur_bb_returned() {
+        let is_needed = stmt.contains_label() || (matches!(stmt, tree::Stmt::Decl(_)) && label_ahead());
+        if !is_needed {
+            return;
+        }
+        if !matches!(stmt, tree::Stmt::Labeled(..)) {
+            let unreachable_bb = env.new_bb(program).basic_block(Some(format!("%unreachable_{}", env.new_unreachable_id())));
+            env.layout_mut(program).bbs_mut().extend([unreachable_bb]);
+            env.set_cur_bb(unreachable_bb);
+            env.set_cur_bb_returned(false);
+        }
     }
-}
\ No newline at end of file
+    stmt.generate_koopa(program, env);
+}
+
+fn get_label_bb<'ast>(ident: &'ast str, program: &mut Program, env: &mut IrgenEnv<'ast>) -> BasicBlock {
+    if let Some(bb) = env.get_label(ident) {
+        return bb;
+    }
+    let bb = env.new_bb(program).basic_block(Some(format!("%label_{}", ident)));
+    env.new_label(ident, bb);
+    bb
+}
+
+/// Starts the basic block of a label, which the code before it falls through to.
+fn generate_label<'ast>(ident: &'ast str, program: &mut Program, env: &mut IrgenEnv<'ast>) {
+    let bb = get_label_bb(ident, program, env);
+    if !env.is_cur_bb_returned() {
+        let jump = env.new_value(program).jump(bb);
+        env.new_inst(program).push_key_back(jump).unwrap();
+    }
+    env.layout_mut(program).bbs_mut().extend([bb]);
+    env.set_cur_bb(bb);
+    env.set_cur_bb_returned(false);
+}
+
+fn generate_for<'ast>(
+    for_init: Option<&'ast tree::Stmt<'ast>>,
+    exp: Option<&'ast tree::Expr>,
+    for_step: Option<&'ast tree::Stmt<'ast>>,
+    stmt: &'ast tree::Stmt<'ast>,
+    program: &mut Program,
+    env: &mut IrgenEnv<'ast>,
+) {
+    if let Some(for_init) = for_init {
+        for_init.generate_koopa(program, env);
+    }
+
+    let old_continue_bb = env.get_cur_continue_bb();
+    let old_break_bb = env.get_cur_break_bb();
+
+    let fid = env.new_for_id();
+    let cond_bb = env.new_bb(program).basic_block(Some(format!("%for_cond_{}", fid)));
+    let body_bb = env.new_bb(program).basic_block(Some(format!("%for_body_{}", fid)));
+    let step_bb = env.new_bb(program).basic_block(Some(format!("%for_step_{}", fid)));
+    let end_bb = env.new_bb(program).basic_block(Some(format!("%for_end_{}", fid)));
+    let jump = env.new_value(program).jump(cond_bb);
+    env.new_inst(program).push_key_back(jump).unwrap();
+
+    env.set_cur_continue_bb(Some(step_bb));
+    env.set_cur_break_bb(Some(end_bb));
+
+    env.layout_mut(program).bbs_mut().extend([cond_bb]);
+    env.set_cur_bb(cond_bb);
+    if let Some(exp) = exp {
+        let cond = exp.generate_koopa(program, env);
+        let br = env.new_value(program).branch(cond, body_bb, end_bb);
+        env.new_inst(program).push_key_back(br).unwrap();
+    } else {
+        let jump = env.new_value(program).jump(body_bb);
+        env.new_inst(program).push_key_back(jump).unwrap();
+    }
+
+    env.layout_mut(program).bbs_mut().extend([body_bb]);
+    env.set_cur_bb(body_bb);
+    env.set_cur_bb_returned(false);
+    stmt.generate_koopa(program, env);
+    if !env.is_cur_bb_returned() {
+        let jump = env.new_value(program).jump(step_bb);
+        env.new_inst(program).push_key_back(jump).unwrap();
+    }
+
+    env.layout_mut(program).bbs_mut().extend([step_bb]);
+    env.set_cur_bb(step_bb);
+    env.set_cur_bb_returned(false);
+    if let Some(for_step) = for_step {
+        for_step.generate_koopa(program, env);
+    }
+    let jump = env.new_value(program).jump(cond_bb);
+    env.new_inst(program).push_key_back(jump).unwrap();
+
+    env.layout_mut(program).bbs_mut().extend([end_bb]);
+    env.set_cur_bb(end_bb);
+    env.set_cur_bb_returned(false);
+
+    env.set_cur_continue_bb(old_continue_bb);
+    env.set_cur_break_bb(old_break_bb);
+}
+
+/// A switch needs at least this many cases to be dispatched through a jump table.
+const JUMP_TABLE_MIN_CASES: usize = 4;
+/// Its case values must also cover at least half of their range.
+const JUMP_TABLE_MAX_RANGE_PER_CASE: i64 = 2;
+
+/// Dispatches through a jump table when the cases are dense enough, and with a
+/// binary search otherwise.
+fn generate_switch<'ast>(exp: &'ast tree::Expr, switch_item_list: &'ast [tree::SwitchItem<'ast>], program: &mut Program, env: &mut IrgenEnv<'ast>) {
+    let sid = env.new_switch_id();
+    let end_bb = env.new_bb(program).basic_block(Some(format!("%switch_end_{}", sid)));
+
+    // Collect the labels first, since the dispatch code has to know all of them.
+    let mut cases: Vec<(i32, BasicBlock)> = vec![];
+    let mut default_bb = None;
+    let mut label_bbs = vec![];
+    for switch_item in switch_item_list {
+        match switch_item {
+            tree::SwitchItem::Case(val) => {
+                let bb = env.new_bb(program).basic_block(Some(format!("%case_{}_{}", sid, cases.len())));
+                cases.push((*val, bb));
+                label_bbs.push(bb);
+            },
+            tree::SwitchItem::Default => {
+                let bb = env.new_bb(program).basic_block(Some(format!("%default_{}", sid)));
+                default_bb = Some(bb);
+                label_bbs.push(bb);
+            },
+            tree::SwitchItem::Stmt(_) => {},
+        }
+    }
+
+    let cond = exp.generate_koopa(program, env);
+    cases.sort_by_key(|&(case_val, _)| case_val);
+    let default_bb = default_bb.unwrap_or(end_bb);
+    let is_dense = match (cases.first(), cases.last()) {
+        (Some(&(min, _)), Some(&(max, _))) => {
+            let range = max as i64 - min as i64 + 1;
+            cases.len() >= JUMP_TABLE_MIN_CASES && range <= JUMP_TABLE_MAX_RANGE_PER_CASE * cases.len() as i64
+        },
+        _ => false,
+    };
+    if is_dense {
+        generate_switch_table(cond, &cases, default_bb, sid, program, env);
+    } else {
+        let mut search_id = 0;
+        generate_switch_search(cond, &cases, default_bb, sid, &mut search_id, program, env);
+    }
+
+    let old_break_bb = env.get_cur_break_bb();
+    env.set_cur_break_bb(Some(end_bb));
+
+    // Anything before the first label is unreachable.
+    env.set_cur_bb_returned(true);
+    let mut label_bbs = label_bbs.into_iter();
+    for (i, switch_item) in switch_item_list.iter().enumerate() {
+        match switch_item {
+            tree::SwitchItem::Case(_) | tree::SwitchItem::Default => {
+                let bb = label_bbs.next().unwrap();
+                if !env.is_cur_bb_returned() {
+                    let jump = env.new_value(program).jump(bb);
+                    env.new_inst(program).push_key_back(jump).unwrap();
+                }
+                env.layout_mut(program).bbs_mut().extend([bb]);
+                env.set_cur_bb(bb);
+                env.set_cur_bb_returned(false);
+            },
+            tree::SwitchItem::Stmt(stmt) => {
+                let label_ahead = || switch_item_list[i + 1..].iter().any(|item| match item {
+                    tree::SwitchItem::Stmt(stmt) => stmt.contains_label(),
+                    tree::SwitchItem::Case(_) | tree::SwitchItem::Default => true,
+                });
+                generate_block_item(stmt, label_ahead, program, env);
+            },
+        }
+    }
+    if !env.is_cur_bb_returned() {
+        let jump = env.new_value(program).jump(end_bb);
+        env.new_inst(program).push_key_back(jump).unwrap();
+    }
+
+    env.set_cur_break_bb(old_break_bb);
+
+    env.layout_mut(program).bbs_mut().extend([end_bb]);
+    env.set_cur_bb(end_bb);
+    env.set_cur_bb_returned(false);
+}
+
+/// Emits a chain of `eq`/`br` blocks, and records it as a jump table for the
+/// backend to replace.
+fn generate_switch_table<'ast>(cond: Value, cases: &[(i32, BasicBlock)], default_bb: BasicBlock, sid: i32, program: &mut Program, env: &mut IrgenEnv<'ast>) {
+    let mut table_bb = env.new_bb(program).basic_block(Some(format!("%switch_table_{}", sid)));
+    let jump = env.new_value(program).jump(table_bb);
+    env.new_inst(program).push_key_back(jump).unwrap();
+    let mut chain = vec![];
+    for (i, &(case_val, case_bb)) in cases.iter().enumerate() {
+        chain.push(table_bb);
+        env.layout_mut(program).bbs_mut().extend([table_bb]);
+        env.set_cur_bb(table_bb);
+        let next_bb = if i + 1 < cases.len() {
+            env.new_bb(program).basic_block(Some(format!("%switch_table_{}_{}", sid, i + 1)))
+        } else {
+            default_bb
+        };
+        let case_val = env.new_value(program).integer(case_val);
+        let eq = env.new_value(program).binary(BinaryOp::Eq, cond, case_val);
+        env.new_inst(program).push_key_back(eq).unwrap();
+        let br = env.new_value(program).branch(eq, case_bb, next_bb);
+        env.new_inst(program).push_key_back(br).unwrap();
+        table_bb = next_bb;
+    }
+    env.new_jump_table(JumpTable { cond, cases: cases.to_vec(), default_bb, chain });
+}
+
+/// Emits a binary search over the sorted case values.
+fn generate_switch_search<'ast>(cond: Value, cases: &[(i32, BasicBlock)], default_bb: BasicBlock, sid: i32, search_id: &mut i32, program: &mut Program, env: &mut IrgenEnv<'ast>) {
+    if cases.len() <= 2 {
+        for (i, &(case_val, case_bb)) in cases.iter().enumerate() {
+            let next_bb = if i + 1 < cases.len() {
+                *search_id += 1;
+                env.new_bb(program).basic_block(Some(format!("%switch_search_{}_{}", sid, search_id)))
+            } else {
+                default_bb
+            };
+            let case_val = env.new_value(program).integer(case_val);
+            let eq = env.new_value(program).binary(BinaryOp::Eq, cond, case_val);
+            env.new_inst(program).push_key_back(eq).unwrap();
+            let br = env.new_value(program).branch(eq, case_bb, next_bb);
+            env.new_inst(program).push_key_back(br).unwrap();
+            if next_bb != default_bb {
+                env.layout_mut(program).bbs_mut().extend([next_bb]);
+                env.set_cur_bb(next_bb);
+            }
+        }
+        if cases.is_empty() {
+            let jump = env.new_value(program).jump(default_bb);
+            env.new_inst(program).push_key_back(jump).unwrap();
+        }
+        return;
+    }
+
+    let mid = cases.len() / 2;
+    *search_id += 1;
+    let lower_bb = env.new_bb(program).basic_block(Some(format!("%switch_search_{}_{}", sid, search_id)));
+    *search_id += 1;
+    let upper_bb = env.new_bb(program).basic_block(Some(format!("%switch_search_{}_{}", sid, search_id)));
+    let mid_val = env.new_value(program).integer(cases[mid].0);
+    let lt = env.new_value(program).binary(BinaryOp::Lt, cond, mid_val);
+    env.new_inst(program).push_key_back(lt).unwrap();
+    let br = env.new_value(program).branch(lt, lower_bb, upper_bb);
+    env.new_inst(program).push_key_back(br).unwrap();
+
+    env.layout_mut(program).bbs_mut().extend([lower_bb]);
+    env.set_cur_bb(lower_bb);
+    generate_switch_search(cond, &cases[..mid], default_bb, sid, search_id, program, env);
+    env.layout_mut(program).bbs_mut().extend([upper_bb]);
+    env.set_cur_bb(upper_bb);
+    generate_switch_search(cond, &cases[mid..], default_bb, sid, search_id, program, env);
+}
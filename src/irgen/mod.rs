@@ -1,59 +1,47 @@
 mod env;
-mod eval;
-mod exp_type;
 mod gen;
-mod symbol;
+mod ty;
 
-use crate::ast::CompUnit;
+use crate::sema::tree::CompUnit;
 use env::IrgenEnv;
 use gen::GenerateKoopa;
-use koopa::ir::Program;
-use std::fmt;
-use std::result::Result;
+use koopa::ir::{BasicBlock, Function, Program, Value};
+use std::collections::{HashMap, HashSet};
 
-/// Generates Koopa IR program for the given compile unit (ASTs).
-pub fn generate_koopa_program(comp_unit: &CompUnit) -> Result<Program, IrgenError> {
-    let mut program = Program::new();
-    comp_unit.generate_koopa(&mut program, &mut IrgenEnv::new())?;
-    Ok(program)
+/// The Koopa IR of a translation unit or of the linked program, with what the
+/// linker and codegen need to know about it that the IR can't express.
+pub struct Module {
+    pub program: Program,
+    pub extern_globals: HashSet<Value>, // Placeholders for the globals defined in other translation units.
+    pub library_funcs: HashSet<Function>, // Declarations of the runtime library.
+    pub variadic_funcs: HashSet<Function>, // Functions whose last two parameters are an array of the extra arguments and its length, which codegen passes on one by one.
+    pub string_globals: HashSet<Value>, // The arrays of the string literals, which are private and read-only.
+    pub static_globals: HashSet<Value>, // Static variables, which are private.
+    pub jump_tables: HashMap<Function, Vec<JumpTable>>, // The dense switches of each function.
 }
 
-pub enum IrgenError {
-    UnknownType,
-    SymbolDeclaredMoreThanOnce,
-    SymbolUndeclared,
-    AssignToConst,
-    InitializeConstWithVariable,
-    BreakOutsideLoop,
-    ContinueOutsideLoop,
-    UseFunctionAsVariable,
-    FunctionUndeclared,
-    InitializeConstWithFunctionCall,
-    ReturnWithExpressionInVoidFunction,
-    UsingVoidValue,
+/// A dense `switch` that codegen turns into a jump table. In the IR it is a
+/// chain of `eq`/`br` blocks, which the table replaces as a whole.
+pub struct JumpTable {
+    pub cond: Value,
+    pub cases: Vec<(i32, BasicBlock)>, // Sorted by value.
+    pub default_bb: BasicBlock,
+    pub chain: Vec<BasicBlock>, // The blocks of the chain, starting with the one jumped to.
 }
 
-impl fmt::Display for IrgenError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::UnknownType => write!(f, "Unknown variable type"),
-            Self::SymbolDeclaredMoreThanOnce => write!(f, "Symbol declared more than once"),
-            Self::SymbolUndeclared => write!(f, "Symbol undeclared"),
-            Self::AssignToConst => write!(f, "Assigning to a const symbol"),
-            Self::InitializeConstWithVariable => write!(f, "Initializing a const symbol with a variable"),
-            Self::BreakOutsideLoop => write!(f, "Break statement outside loop"),
-            Self::ContinueOutsideLoop => write!(f, "Continue statement outside loop"),
-            Self::UseFunctionAsVariable => write!(f, "Using a function as a variable"),
-            Self::FunctionUndeclared => write!(f, "Function undeclared"),
-            Self::InitializeConstWithFunctionCall => write!(f, "Initializing a const symbol with a function call"),
-            Self::ReturnWithExpressionInVoidFunction => write!(f, "Returning with an expression in a void function"),
-            Self::UsingVoidValue => write!(f, "Using a void value"),
-        }
-    }
-}
-
-impl fmt::Debug for IrgenError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self)
+/// Generates Koopa IR module for the given checked compile unit. Every error
+/// is caught by the semantic analysis, so this can't fail.
+pub fn generate_koopa_module(comp_unit: &CompUnit) -> Module {
+    let mut program = Program::new();
+    let mut env = IrgenEnv::new(comp_unit);
+    comp_unit.generate_koopa(&mut program, &mut env);
+    Module {
+        program,
+        extern_globals: env.take_extern_globals(),
+        library_funcs: env.take_library_funcs(),
+        variadic_funcs: env.take_variadic_funcs(),
+        string_globals: env.take_string_globals(),
+        static_globals: env.take_static_globals(),
+        jump_tables: env.take_jump_tables(),
     }
 }
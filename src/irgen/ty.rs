@@ -0,0 +1,20 @@
+use koopa::ir::Type;
+
+use super::env::IrgenEnv;
+use crate::sema::ty::Ty;
+
+pub trait ToKoopa {
+    fn to_koopa(&self, env: &IrgenEnv) -> Type;
+}
+
+impl ToKoopa for Ty {
+    fn to_koopa(&self, env: &IrgenEnv) -> Type {
+        match self {
+            Self::Int | Self::Char => Type::get_i32(),
+            Self::Void => Type::get_unit(),
+            Self::Pointer(base) => Type::get_pointer(base.to_koopa(env)),
+            Self::Array(elem, len) => Type::get_array(elem.to_koopa(env), *len),
+            Self::Struct(struct_id) => Type::get_array(Type::get_i32(), env.get_struct_size(*struct_id)),
+        }
+    }
+}
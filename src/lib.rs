@@ -1,3 +1,9 @@
 pub mod ast;
 pub mod codegen;
+pub mod diagnostic;
 pub mod irgen;
+pub mod link;
+pub mod literal;
+pub mod preprocess;
+pub mod sema;
+pub mod span;
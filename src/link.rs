@@ -0,0 +1,395 @@
+use crate::irgen::{JumpTable, Module};
+use koopa::ir::builder::{BasicBlockBuilder, GlobalInstBuilder, LocalBuilder, LocalInstBuilder, ValueBuilder};
+use koopa::ir::{BasicBlock, Function, FunctionData, Program, TypeKind, Value, ValueKind};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::result::Result;
+
+/// Links the modules of several translation units into one Koopa IR module.
+/// A global or a function may be defined in only one of them, and whatever
+/// is used through an `extern` declaration or called through a prototype must
+/// be defined in some module, unless it belongs to the runtime library.
+pub fn link(modules: &[Module]) -> Result<Module, LinkError> {
+    let mut linker = Linker::new();
+    linker.link_globals(modules)?;
+    linker.link_funcs(modules)?;
+    linker.name_internal_globals();
+    for (i, module) in modules.iter().enumerate() {
+        for &func in module.program.func_layout() {
+            if is_definition(module.program.func(func)) {
+                linker.copy_body(i, module, func)?;
+            }
+        }
+    }
+    Ok(Module {
+        program: linker.program,
+        extern_globals: HashSet::new(),
+        library_funcs: linker.library_funcs,
+        variadic_funcs: linker.variadic_funcs,
+        string_globals: linker.string_globals,
+        static_globals: linker.static_globals,
+        jump_tables: linker.jump_tables,
+    })
+}
+
+pub enum LinkError {
+    DuplicateDefinition(String),
+    UndefinedSymbol(String),
+    ConflictingTypes(String),
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::DuplicateDefinition(name) => write!(f, "Symbol `{}` defined more than once", name),
+            Self::UndefinedSymbol(name) => write!(f, "Undefined reference to `{}`", name),
+            Self::ConflictingTypes(name) => write!(f, "Conflicting types for `{}`", name),
+        }
+    }
+}
+
+impl fmt::Debug for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+struct Linker {
+    program: Program,
+    names: HashSet<String>, // The names taken by the globals and the functions of the linked program.
+    globals: HashMap<(usize, Value), Value>, // From the globals of each module to the linked ones.
+    funcs: HashMap<(usize, Function), Function>, // Likewise for the functions.
+    undefined_funcs: HashSet<Function>, // Linked declarations that neither a module nor the runtime library defines.
+    library_funcs: HashSet<Function>,
+    variadic_funcs: HashSet<Function>,
+    string_globals: HashSet<Value>,
+    static_globals: HashSet<Value>,
+    internal_globals: Vec<(Value, String)>, // Linked internal globals with the names they had in their modules.
+    jump_tables: HashMap<Function, Vec<JumpTable>>,
+}
+
+impl Linker {
+    fn new() -> Self {
+        Self {
+            program: Program::new(),
+            names: HashSet::new(),
+            globals: HashMap::new(),
+            funcs: HashMap::new(),
+            undefined_funcs: HashSet::new(),
+            library_funcs: HashSet::new(),
+            variadic_funcs: HashSet::new(),
+            string_globals: HashSet::new(),
+            static_globals: HashSet::new(),
+            internal_globals: Vec::new(),
+            jump_tables: HashMap::new(),
+        }
+    }
+
+    fn link_globals(&mut self, modules: &[Module]) -> Result<(), LinkError> {
+        let mut defined = HashMap::new();
+        for (i, module) in modules.iter().enumerate() {
+            for &global in module.program.inst_layout() {
+                if module.extern_globals.contains(&global) {
+                    continue;
+                }
+                let data = module.program.borrow_value(global);
+                let name = data.name().clone().unwrap();
+                let init = match data.kind() {
+                    ValueKind::GlobalAlloc(global_alloc) => self.copy_init(&module.program, global_alloc.init()),
+                    _ => unreachable!(),
+                };
+                let linked = self.program.new_value().global_alloc(init);
+                // Internal globals of different modules may share a name, so
+                // those are renamed instead, once every external name is taken.
+                if is_internal(module, global) {
+                    self.internal_globals.push((linked, name));
+                } else {
+                    self.take_name(&name)?;
+                    self.program.set_value_name(linked, Some(name.clone()));
+                    defined.insert(name, linked);
+                }
+                if module.string_globals.contains(&global) {
+                    self.string_globals.insert(linked);
+                }
+                if module.static_globals.contains(&global) {
+                    self.static_globals.insert(linked);
+                }
+                self.globals.insert((i, global), linked);
+            }
+        }
+        for (i, module) in modules.iter().enumerate() {
+            for &global in module.program.inst_layout() {
+                if !module.extern_globals.contains(&global) {
+                    continue;
+                }
+                let data = module.program.borrow_value(global);
+                let name = data.name().as_ref().unwrap();
+                let Some(&linked) = defined.get(name) else {
+                    // Like a prototype, an extern that is never used needs no
+                    // definition.
+                    if data.used_by().is_empty() {
+                        continue;
+                    }
+                    return Err(LinkError::UndefinedSymbol(name[1..].to_string()));
+                };
+                if self.program.borrow_value(linked).ty() != data.ty() {
+                    return Err(LinkError::ConflictingTypes(name[1..].to_string()));
+                }
+                self.globals.insert((i, global), linked);
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates the linked functions in the order they appear in the modules,
+    /// and resolves every declaration to the definition of the same name.
+    fn link_funcs(&mut self, modules: &[Module]) -> Result<(), LinkError> {
+        let mut defined_names = HashSet::new();
+        let mut library_names = HashSet::new();
+        for module in modules {
+            for &func in module.program.func_layout() {
+                let data = module.program.func(func);
+                if is_definition(data) {
+                    if !defined_names.insert(data.name()) {
+                        return Err(LinkError::DuplicateDefinition(data.name()[1..].to_string()));
+                    }
+                } else if module.library_funcs.contains(&func) {
+                    library_names.insert(data.name());
+                }
+            }
+        }
+
+        let mut linked_funcs = HashMap::new();
+        for (i, module) in modules.iter().enumerate() {
+            for &func in module.program.func_layout() {
+                let data = module.program.func(func);
+                let (params_ty, ret_ty) = match data.ty().kind() {
+                    TypeKind::Function(params_ty, ret_ty) => (params_ty.clone(), ret_ty.clone()),
+                    _ => unreachable!(),
+                };
+                if is_definition(data) {
+                    self.take_name(data.name())?;
+                    let params = data.params().iter()
+                        .map(|&param| data.dfg().value(param).name().clone())
+                        .zip(params_ty)
+                        .collect();
+                    let linked = self.program.new_func(FunctionData::with_param_names(data.name().to_string(), params, ret_ty));
+                    linked_funcs.insert(data.name(), linked);
+                    self.funcs.insert((i, func), linked);
+                } else if !defined_names.contains(data.name()) && !linked_funcs.contains_key(data.name()) {
+                    self.take_name(data.name()).map_err(|_| LinkError::ConflictingTypes(data.name()[1..].to_string()))?;
+                    let linked = self.program.new_func(FunctionData::new_decl(data.name().to_string(), params_ty, ret_ty));
+                    linked_funcs.insert(data.name(), linked);
+                    if library_names.contains(data.name()) {
+                        self.library_funcs.insert(linked);
+                    } else {
+                        self.undefined_funcs.insert(linked);
+                    }
+                }
+            }
+        }
+
+        for (i, module) in modules.iter().enumerate() {
+            for &func in module.program.func_layout() {
+                let data = module.program.func(func);
+                if is_definition(data) {
+                    continue;
+                }
+                let linked = linked_funcs[data.name()];
+                if self.program.func(linked).ty() != data.ty() {
+                    return Err(LinkError::ConflictingTypes(data.name()[1..].to_string()));
+                }
+                self.funcs.insert((i, func), linked);
+            }
+        }
+
+        // The types agree, so a function is variadic in every module or in
+        // none of them.
+        for (&(i, func), &linked) in &self.funcs {
+            if modules[i].variadic_funcs.contains(&func) {
+                self.variadic_funcs.insert(linked);
+            }
+        }
+        Ok(())
+    }
+
+    /// Names the internal globals after the external globals and the functions,
+    /// whose names can't change, so that an internal global never takes one.
+    fn name_internal_globals(&mut self) {
+        for (linked, name) in std::mem::take(&mut self.internal_globals) {
+            let name = self.take_unique_name(name);
+            self.program.set_value_name(linked, Some(name));
+        }
+    }
+
+    /// Copies the basic blocks and the instructions of `func` of the `i`-th
+    /// module into its linked function, keeping their names, along with its
+    /// jump tables.
+    fn copy_body(&mut self, i: usize, module: &Module, func: Function) -> Result<(), LinkError> {
+        let data = module.program.func(func);
+        let linked = self.funcs[&(i, func)];
+        let mut values: HashMap<Value, Value> = data.params().iter()
+            .copied()
+            .zip(self.program.func(linked).params().iter().copied())
+            .collect();
+        let mut bbs = HashMap::new();
+        for &bb in data.layout().bbs().keys() {
+            let linked_func = self.program.func_mut(linked);
+            let linked_bb = linked_func.dfg_mut().new_bb().basic_block(data.dfg().bb(bb).name().clone());
+            linked_func.layout_mut().bbs_mut().push_key_back(linked_bb).unwrap();
+            bbs.insert(bb, linked_bb);
+        }
+        for (&bb, node) in data.layout().bbs() {
+            for &inst in node.insts().keys() {
+                let linked_inst = self.copy_inst(i, data, inst, &values, &bbs, linked)?;
+                let linked_func = self.program.func_mut(linked);
+                linked_func.dfg_mut().set_value_name(linked_inst, data.dfg().value(inst).name().clone());
+                linked_func.layout_mut().bb_mut(bbs[&bb]).insts_mut().push_key_back(linked_inst).unwrap();
+                values.insert(inst, linked_inst);
+            }
+        }
+        for jump_table in module.jump_tables.get(&func).into_iter().flatten() {
+            let jump_table = JumpTable {
+                cond: self.copy_operand(i, data, jump_table.cond, &values, linked),
+                cases: jump_table.cases.iter().map(|&(case_val, bb)| (case_val, bbs[&bb])).collect(),
+                default_bb: bbs[&jump_table.default_bb],
+                chain: jump_table.chain.iter().map(|bb| bbs[bb]).collect(),
+            };
+            self.jump_tables.entry(linked).or_default().push(jump_table);
+        }
+        Ok(())
+    }
+
+    fn copy_inst(
+        &mut self,
+        i: usize,
+        data: &FunctionData,
+        inst: Value,
+        values: &HashMap<Value, Value>,
+        bbs: &HashMap<BasicBlock, BasicBlock>,
+        linked: Function,
+    ) -> Result<Value, LinkError> {
+        let inst_data = data.dfg().value(inst);
+        let linked_inst = match inst_data.kind() {
+            ValueKind::Alloc(_) => {
+                let ty = match inst_data.ty().kind() {
+                    TypeKind::Pointer(base) => base.clone(),
+                    _ => unreachable!(),
+                };
+                self.new_value(linked).alloc(ty)
+            },
+            ValueKind::Load(load) => {
+                let src = self.copy_operand(i, data, load.src(), values, linked);
+                self.new_value(linked).load(src)
+            },
+            ValueKind::Store(store) => {
+                let value = self.copy_operand(i, data, store.value(), values, linked);
+                let dest = self.copy_operand(i, data, store.dest(), values, linked);
+                self.new_value(linked).store(value, dest)
+            },
+            ValueKind::GetPtr(get_ptr) => {
+                let src = self.copy_operand(i, data, get_ptr.src(), values, linked);
+                let index = self.copy_operand(i, data, get_ptr.index(), values, linked);
+                self.new_value(linked).get_ptr(src, index)
+            },
+            ValueKind::GetElemPtr(get_elem_ptr) => {
+                let src = self.copy_operand(i, data, get_elem_ptr.src(), values, linked);
+                let index = self.copy_operand(i, data, get_elem_ptr.index(), values, linked);
+                self.new_value(linked).get_elem_ptr(src, index)
+            },
+            ValueKind::Binary(binary) => {
+                let lhs = self.copy_operand(i, data, binary.lhs(), values, linked);
+                let rhs = self.copy_operand(i, data, binary.rhs(), values, linked);
+                self.new_value(linked).binary(binary.op(), lhs, rhs)
+            },
+            ValueKind::Branch(branch) => {
+                let cond = self.copy_operand(i, data, branch.cond(), values, linked);
+                self.new_value(linked).branch(cond, bbs[&branch.true_bb()], bbs[&branch.false_bb()])
+            },
+            ValueKind::Jump(jump) => {
+                self.new_value(linked).jump(bbs[&jump.target()])
+            },
+            ValueKind::Call(call) => {
+                let callee = self.funcs[&(i, call.callee())];
+                if self.undefined_funcs.contains(&callee) {
+                    return Err(LinkError::UndefinedSymbol(self.program.func(callee).name()[1..].to_string()));
+                }
+                let args = call.args().iter()
+                    .map(|&arg| self.copy_operand(i, data, arg, values, linked))
+                    .collect();
+                self.new_value(linked).call(callee, args)
+            },
+            ValueKind::Return(ret) => {
+                let value = ret.value().map(|value| self.copy_operand(i, data, value, values, linked));
+                self.new_value(linked).ret(value)
+            },
+            _ => unreachable!(),
+        };
+        Ok(linked_inst)
+    }
+
+    /// Maps an operand to the linked function. Constants aren't in the
+    /// layout, so they are created on their first use.
+    fn copy_operand(&mut self, i: usize, data: &FunctionData, value: Value, values: &HashMap<Value, Value>, linked: Function) -> Value {
+        if value.is_global() {
+            return self.globals[&(i, value)];
+        }
+        if let Some(&linked_value) = values.get(&value) {
+            return linked_value;
+        }
+        let value_data = data.dfg().value(value);
+        match value_data.kind() {
+            ValueKind::Integer(int) => self.new_value(linked).integer(int.value()),
+            ValueKind::ZeroInit(_) => self.new_value(linked).zero_init(value_data.ty().clone()),
+            ValueKind::Undef(_) => self.new_value(linked).undef(value_data.ty().clone()),
+            _ => unreachable!(),
+        }
+    }
+
+    fn copy_init(&mut self, src: &Program, init: Value) -> Value {
+        let init_data = src.borrow_value(init);
+        match init_data.kind() {
+            ValueKind::Integer(int) => self.program.new_value().integer(int.value()),
+            ValueKind::ZeroInit(_) => self.program.new_value().zero_init(init_data.ty().clone()),
+            ValueKind::Undef(_) => self.program.new_value().undef(init_data.ty().clone()),
+            ValueKind::Aggregate(aggregate) => {
+                let elems = aggregate.elems().iter().map(|&elem| self.copy_init(src, elem)).collect();
+                self.program.new_value().aggregate(elems)
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    fn new_value(&mut self, func: Function) -> LocalBuilder<'_> {
+        self.program.func_mut(func).dfg_mut().new_value()
+    }
+
+    fn take_name(&mut self, name: &str) -> Result<(), LinkError> {
+        if !self.names.insert(name.to_string()) {
+            return Err(LinkError::DuplicateDefinition(name[1..].to_string()));
+        }
+        Ok(())
+    }
+
+    /// Takes `name`, or the first free name made by appending a number to it.
+    fn take_unique_name(&mut self, name: String) -> String {
+        let mut unique_name = name.clone();
+        let mut suffix = 0;
+        while self.names.contains(&unique_name) {
+            suffix += 1;
+            unique_name = format!("{}_{}", name, suffix);
+        }
+        self.names.insert(unique_name.clone());
+        unique_name
+    }
+}
+
+fn is_definition(data: &FunctionData) -> bool {
+    data.layout().entry_bb().is_some()
+}
+
+/// Whether a global is private to its module, like a string literal or a
+/// static variable, which codegen doesn't export either.
+fn is_internal(module: &Module, global: Value) -> bool {
+    module.string_globals.contains(&global) || module.static_globals.contains(&global)
+}
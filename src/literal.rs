@@ -0,0 +1,57 @@
+/// Decodes the escape sequences in the body of a string or character
+/// literal, that is, the text between the quotes.
+///
+/// Besides the simple escapes like `\n`, octal (`\101`) and hexadecimal
+/// (`\x41`) escapes are supported. Each of them stands for a single byte, so
+/// the result is a byte string rather than a `String`.
+pub fn unescape(body: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        let c = chars.next().ok_or("incomplete escape sequence")?;
+        let byte = match c {
+            'a' => 0x07,
+            'b' => 0x08,
+            'f' => 0x0c,
+            'n' => b'\n',
+            'r' => b'\r',
+            't' => b'\t',
+            'v' => 0x0b,
+            '\\' | '\'' | '"' | '?' => c as u8,
+            '0'..='7' => {
+                let mut val = c.to_digit(8).unwrap();
+                for _ in 0..2 {
+                    match chars.peek().and_then(|c| c.to_digit(8)) {
+                        Some(digit) => {
+                            val = val * 8 + digit;
+                            chars.next();
+                        }
+                        None => break,
+                    }
+                }
+                u8::try_from(val).map_err(|_| "octal escape sequence out of range")?
+            }
+            'x' => {
+                let mut val: u32 = 0;
+                let mut digits = 0;
+                while let Some(digit) = chars.peek().and_then(|c| c.to_digit(16)) {
+                    val = val.saturating_mul(16).saturating_add(digit);
+                    digits += 1;
+                    chars.next();
+                }
+                if digits == 0 {
+                    return Err("\\x used with no following hex digits".to_string());
+                }
+                u8::try_from(val).map_err(|_| "hex escape sequence out of range")?
+            }
+            c => return Err(format!("unknown escape sequence '\\{}'", c)),
+        };
+        bytes.push(byte);
+    }
+    Ok(bytes)
+}
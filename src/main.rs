@@ -1,8 +1,14 @@
 use koopa::back::KoopaGenerator;
 use koopa::ir::Program;
-use lalrpop_util::lalrpop_mod;
+use lalrpop_util::lexer::Token;
+use lalrpop_util::{lalrpop_mod, ErrorRecovery, ParseError};
 use sysy_compiler::codegen;
+use sysy_compiler::diagnostic::Diagnostic;
 use sysy_compiler::irgen;
+use sysy_compiler::link;
+use sysy_compiler::preprocess::preprocess;
+use sysy_compiler::sema;
+use sysy_compiler::span::{LineIndex, Span};
 use std::env::args;
 use std::fs::read_to_string;
 use std::io::Write;
@@ -11,7 +17,8 @@ use std::{fmt, io, fs::File};
 use std::result::Result;
 
 // Include the parser generated by lalrpop
-lalrpop_mod!(sysy);
+// The error list has to be a `&mut Vec` for the parser to push recovered errors into it
+lalrpop_mod!(#[allow(clippy::ptr_arg)] sysy);
 
 fn main() {
   if let Err(err) = try_main() {
@@ -24,32 +31,140 @@ fn try_main() -> Result<(), Error> {
   // Parse Command Line Arguments
   let CommandLineArgs {
     mode,
-    input,
+    inputs,
     output,
+    warnings_as_errors,
+    extended,
   } = CommandLineArgs::parse()?;
 
+  // Compile each input file into a module on its own
+  let mut modules = vec![];
+  for input_path in &inputs {
+    modules.push(compile_module(input_path, warnings_as_errors, extended)?);
+  }
+
+  // Link the modules, resolving the declarations of one file to the
+  // definitions in another
+  let koopa_module = link::link(&modules)
+    .map_err(Error::Link)?;
+
+  // Generate output according to the mode [Koopa or Riscv]
+  match mode {
+    Mode::Koopa => print_koopa_ir(&koopa_module.program, &output)?,
+    Mode::Riscv => print_riscv_asm(&koopa_module, &output)?,
+  }
+
+  Ok(())
+}
+
+fn compile_module(input_path: &String, warnings_as_errors: bool, extended: bool) -> Result<irgen::Module, Error> {
   // Read input file
-  let input = read_to_string(input)
+  let source = read_to_string(input_path)
     .map_err(Error::File)?;
 
-  // Parse input file with the parser generated by lalrpop
-  let comp_unit = sysy::CompUnitParser::new()
-    .parse(&input)
-    .map_err(|_| Error::Parse)?;
+  // Run the preprocessor. It keeps every line in place, so later diagnostics
+  // can be rendered against its output with the original line numbers
+  let input = preprocess(&source)
+    .map_err(|errors| {
+      let line_index = LineIndex::new(&source);
+      let rendered = errors
+        .into_iter()
+        .map(|err| err.render(input_path, &source, &line_index))
+        .collect();
+      Error::Preprocess(rendered)
+    })?;
+  let line_index = LineIndex::new(&input);
+
+  // Parse input file with the parser generated by lalrpop, recovering from as many syntax errors as possible
+  let mut errors = vec![];
+  let result = sysy::CompUnitParser::new().parse(&mut errors, &input);
+  let mut errors: Vec<_> = errors.into_iter().map(|ErrorRecovery { error, .. }| error).collect();
+  if let Err(err) = result.as_ref() {
+    errors.push(err.clone());
+  }
+  if !errors.is_empty() {
+    let rendered: Vec<_> = errors
+      .into_iter()
+      .map(|err| parse_error_to_diagnostic(err, &input).render(input_path, &input, &line_index))
+      .collect();
+    return Err(Error::Parse(rendered));
+  }
+  let comp_unit = result.unwrap();
 
   // print!("{:?}", comp_unit);
 
+  // Check the program, resolving names and typing expressions
+  let mut tree = sema::analyze(&comp_unit, extended)
+    .map_err(|err| {
+      let rendered = render_sema_error(&err, "error occured during semantic analysis", input_path, &input, &line_index);
+      Error::Analyze(err, rendered)
+    })?;
+
+  // Report the warnings, or stop at the first one if they are errors
+  if warnings_as_errors && !tree.warnings.is_empty() {
+    let err = tree.warnings.remove(0);
+    let rendered = render_sema_error(&err, "error occured during semantic analysis", input_path, &input, &line_index);
+    return Err(Error::Analyze(err, rendered));
+  }
+  for warning in &tree.warnings {
+    match render_sema_error(warning, "warning", input_path, &input, &line_index) {
+      Some(rendered) => eprintln!("{}", rendered),
+      None => eprintln!("warning: {}", warning),
+    }
+  }
+
   // Generate Koopa IR
-  let koopa_program = irgen::generate_koopa_program(&comp_unit)
-    .map_err(Error::GenerateIr)?;
+  Ok(irgen::generate_koopa_module(&tree))
+}
 
-  // Generate output according to the mode [Koopa or Riscv]
-  match mode {
-    Mode::Koopa => print_koopa_ir(&koopa_program, &output)?,
-    Mode::Riscv => print_riscv_asm(&koopa_program, &output)?,
+fn render_sema_error(err: &sema::SemaError, prefix: &str, input_path: &str, input: &str, line_index: &LineIndex) -> Option<String> {
+  err.span().map(|span| {
+    let message = format!("{}: {}", prefix, err);
+    Diagnostic::new(span, message).render(input_path, input, line_index)
+  })
+}
+
+fn parse_error_to_diagnostic(err: ParseError<usize, Token, Diagnostic>, input: &str) -> Diagnostic {
+  match err {
+    ParseError::InvalidToken { location } => {
+      // The offending character may take more than one byte
+      let len = input[location..].chars().next().map_or(0, char::len_utf8);
+      Diagnostic::new(Span::new(location, location + len), "invalid token".to_string())
+    }
+    ParseError::UnrecognizedEof { location, expected } => {
+      let message = format!("unexpected end of file{}", describe_expected(&expected));
+      Diagnostic::new(Span::new(location, input.len()), message)
+    }
+    ParseError::UnrecognizedToken { token: (l, token, r), expected } => {
+      let message = format!("unexpected token '{}'{}", token, describe_expected(&expected));
+      Diagnostic::new(Span::new(l, r), message)
+    }
+    ParseError::ExtraToken { token: (l, token, r) } => {
+      Diagnostic::new(Span::new(l, r), format!("extra token '{}'", token))
+    }
+    ParseError::User { error } => error,
   }
+}
 
-  Ok(())
+fn describe_expected(expected: &[String]) -> String {
+  let mut names: Vec<String> = vec![];
+  for name in expected {
+    let name = match name.as_str() {
+      "IDENT" => "identifier".to_string(),
+      "DEC_INT_CONST" | "OCT_INT_CONST" | "HEX_INT_CONST" => "integer literal".to_string(),
+      "CHAR_LITERAL" => "character literal".to_string(),
+      "STRING_LITERAL" => "string literal".to_string(),
+      _ => format!("'{}'", name.trim_matches('"')),
+    };
+    if !names.contains(&name) {
+      names.push(name);
+    }
+  }
+  match names.len() {
+    0 => String::new(),
+    1 => format!(", expected {}", names[0]),
+    _ => format!(", expected one of {}", names.join(", ")),
+  }
 }
 
 fn print_koopa_ir(program: &Program, output_path: &String) -> Result<(), Error> { 
@@ -66,8 +181,8 @@ fn print_koopa_ir(program: &Program, output_path: &String) -> Result<(), Error>
   Ok(())
 }
 
-fn print_riscv_asm(program: &Program, output_path: &String) -> Result<(), Error> {
-  let riscv_asm_text = codegen::generate_riscv(program)
+fn print_riscv_asm(module: &irgen::Module, output_path: &String) -> Result<(), Error> {
+  let riscv_asm_text = codegen::generate_riscv(module)
     .map_err(Error::GenerateCode)?;
 
   let mut file = File::create(output_path)
@@ -81,8 +196,10 @@ fn print_riscv_asm(program: &Program, output_path: &String) -> Result<(), Error>
 enum Error {
   InvalidArgs,
   File(io::Error),
-  Parse,
-  GenerateIr(irgen::IrgenError),
+  Preprocess(Vec<String>),
+  Parse(Vec<String>),
+  Analyze(sema::SemaError, Option<String>),
+  Link(link::LinkError),
   Ir2Text,
   GenerateCode(codegen::CodegenError),
   Io(io::Error),
@@ -93,17 +210,26 @@ impl fmt::Display for Error {
     match self {
       Self::InvalidArgs => write!(
         f,
-        r#"Usage: sysy_compiler MODE INPUT -o OUTPUT
+        r#"Usage: sysy_compiler MODE [-Werror] [-ext] INPUT... -o OUTPUT
 
 Options:
-  MODE:   can be `-koopa`, `-riscv` or `-perf`
-  INPUT:  the input SysY source file
-  OUTPUT: the output file"#
+  MODE:    can be `-koopa`, `-riscv` or `-perf`
+  -Werror: treat the warnings as errors
+  -ext:    accept extended SysY, which has pointers
+  INPUT:   the input SysY source files, linked into one program
+  OUTPUT:  the output file"#
       ),
       Self::File(err) => write!(f, "invalid file: {}", err),
-      Self::Parse => write!(f, "error occurred while parsing"),
+      Self::Preprocess(rendered) | Self::Parse(rendered) => {
+        for diagnostic in rendered {
+          writeln!(f, "{}", diagnostic)?;
+        }
+        write!(f, "aborting due to {} previous error(s)", rendered.len())
+      }
       Self::Io(err) => write!(f, "I/O error: {}", err),
-      Self::GenerateIr(err) => write!(f, "error occured while generating IR: {}", err),
+      Self::Analyze(_, Some(rendered)) => write!(f, "{}", rendered),
+      Self::Analyze(err, None) => write!(f, "error occured during semantic analysis: {}", err),
+      Self::Link(err) => write!(f, "error occured while linking: {}", err),
       Self::Ir2Text => write!(f, "error occured while converting IR to text"),
       Self::GenerateCode(err) => write!(f, "error occured while generating asm: {}", err),
     }
@@ -112,16 +238,28 @@ Options:
 
 struct CommandLineArgs {
   mode: Mode,
-  input: String,
+  inputs: Vec<String>,
   output: String,
+  warnings_as_errors: bool,
+  extended: bool,
 }
 
 impl CommandLineArgs {
   fn parse() -> Result<Self, Error> {
-    let mut args = args();
-    args.next();
-    match (args.next(), args.next(), args.next(), args.next()) {
-      (Some(m), Some(input), Some(o), Some(output)) if o == "-o" => {
+    let mut args: Vec<_> = args().skip(1).collect();
+    // The options come right after the mode, in any order
+    let mut warnings_as_errors = false;
+    let mut extended = false;
+    while args.len() > 1 {
+      match args[1].as_str() {
+        "-Werror" => warnings_as_errors = true,
+        "-ext" => extended = true,
+        _ => break,
+      }
+      args.remove(1);
+    }
+    match args.as_slice() {
+      [m, inputs @ .., o, output] if o == "-o" && !inputs.is_empty() => {
         let mode = match m.as_str() {
           "-koopa" => Mode::Koopa,
           "-riscv" => Mode::Riscv,
@@ -129,8 +267,10 @@ impl CommandLineArgs {
         };
         Ok(Self {
           mode,
-          input,
-          output,
+          inputs: inputs.to_vec(),
+          output: output.clone(),
+          warnings_as_errors,
+          extended,
         })
       }
       _ => Err(Error::InvalidArgs),
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
+
+/// Runs a minimal C preprocessor over `source`.
+///
+/// Supports `#include "sylib.h"` (whose declarations are built into the
+/// compiler), object-like `#define`/`#undef`, `#ifdef`/`#ifndef`/`#else`/
+/// `#endif` and `__LINE__`. Calls to `starttime()`/`stoptime()` are rewritten
+/// to `_sysy_starttime(__LINE__)`/`_sysy_stoptime(__LINE__)`, as the macros in
+/// the official `sylib.h` do.
+///
+/// Every line of the output corresponds to the same line of the input, and
+/// directives are replaced by empty lines, so that line numbers in later
+/// diagnostics stay correct.
+pub fn preprocess(source: &str) -> Result<String, Vec<Diagnostic>> {
+    let mut preprocessor = Preprocessor::new();
+    let mut output = String::with_capacity(source.len());
+    let mut offset = 0;
+    for (line_no, line) in source.split_inclusive('\n').enumerate() {
+        let text = line.trim_end_matches(['\n', '\r']);
+        let line_break = &line[text.len()..];
+        preprocessor.process_line(text, offset, line_no + 1, &mut output);
+        output.push_str(line_break);
+        offset += line.len();
+    }
+    for cond in &preprocessor.cond_stack {
+        preprocessor.errors.push(Diagnostic::new(cond.span, "unterminated conditional directive".to_string()));
+    }
+    if preprocessor.errors.is_empty() {
+        Ok(output)
+    } else {
+        Err(preprocessor.errors)
+    }
+}
+
+struct Cond {
+    active: bool, // Whether the lines in the current branch are kept.
+    taken: bool, // Whether any branch of this conditional has been kept.
+    span: Span,
+}
+
+struct Preprocessor {
+    macros: HashMap<String, String>,
+    cond_stack: Vec<Cond>,
+    in_block_comment: bool,
+    errors: Vec<Diagnostic>,
+}
+
+impl Preprocessor {
+    fn new() -> Self {
+        Self {
+            macros: HashMap::new(),
+            cond_stack: Vec::new(),
+            in_block_comment: false,
+            errors: Vec::new(),
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.cond_stack.last().is_none_or(|cond| cond.active)
+    }
+
+    fn process_line(&mut self, text: &str, offset: usize, line_no: usize, output: &mut String) {
+        if !self.in_block_comment && text.trim_start().starts_with('#') {
+            self.process_directive(text, offset);
+            // A block comment left open by the directive goes on in the next
+            // lines, so the output has to open it too.
+            if self.in_block_comment && self.is_active() {
+                output.push_str("/*");
+            }
+        } else if self.is_active() {
+            let mut hidden = vec![];
+            self.expand(text, line_no, &mut hidden, output);
+        } else {
+            // Comments still have to be tracked in skipped lines.
+            self.expand(text, line_no, &mut vec![], &mut String::new());
+        }
+    }
+
+    fn process_directive(&mut self, text: &str, offset: usize) {
+        let start = text.find('#').unwrap() + 1;
+        let rest = self.strip_comments(&text[start..]);
+        let rest = rest.trim();
+        let name_len = rest.find(|c: char| !is_ident_char(c)).unwrap_or(rest.len());
+        let (name, arg) = (&rest[..name_len], rest[name_len..].trim());
+        let span = Span::new(offset, offset + text.len());
+
+        match name {
+            "ifdef" | "ifndef" => {
+                let defined = self.macros.contains_key(arg);
+                let active = self.is_active() && (defined == (name == "ifdef"));
+                self.cond_stack.push(Cond { active, taken: active, span });
+                return;
+            }
+            "else" => {
+                let parent_active = self.cond_stack.len() < 2 || self.cond_stack[self.cond_stack.len() - 2].active;
+                match self.cond_stack.last_mut() {
+                    Some(cond) => {
+                        cond.active = parent_active && !cond.taken;
+                        cond.taken = true;
+                    }
+                    None => self.errors.push(Diagnostic::new(span, "#else without #ifdef".to_string())),
+                }
+                return;
+            }
+            "endif" => {
+                if self.cond_stack.pop().is_none() {
+                    self.errors.push(Diagnostic::new(span, "#endif without #ifdef".to_string()));
+                }
+                return;
+            }
+            _ => {}
+        }
+        if !self.is_active() {
+            return;
+        }
+
+        match name {
+            "include" => {
+                if arg != "\"sylib.h\"" && arg != "<sylib.h>" {
+                    self.errors.push(Diagnostic::new(span, format!("cannot include {}, only \"sylib.h\" is supported", arg)));
+                }
+            }
+            "define" => {
+                let ident_len = arg.find(|c: char| !is_ident_char(c)).unwrap_or(arg.len());
+                let (ident, replacement) = arg.split_at(ident_len);
+                if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+                    self.errors.push(Diagnostic::new(span, "macro name must be an identifier".to_string()));
+                } else if replacement.starts_with('(') {
+                    self.errors.push(Diagnostic::new(span, "function-like macros are not supported".to_string()));
+                } else {
+                    self.macros.insert(ident.to_string(), replacement.trim().to_string());
+                }
+            }
+            "undef" => {
+                self.macros.remove(arg);
+            }
+            _ => {
+                self.errors.push(Diagnostic::new(span, format!("unknown preprocessing directive '#{}'", name)));
+            }
+        }
+    }
+
+    /// Removes the comments from the rest of a directive line, replacing each
+    /// by a space as the lexer would. A block comment that isn't closed on
+    /// this line goes on in the next lines.
+    fn strip_comments(&mut self, text: &str) -> String {
+        let mut stripped = String::with_capacity(text.len());
+        let mut rest = text;
+        while !rest.is_empty() {
+            if rest.starts_with("//") {
+                break;
+            }
+            if let Some(comment) = rest.strip_prefix("/*") {
+                stripped.push(' ');
+                match comment.find("*/") {
+                    Some(end) => rest = &comment[end + 2..],
+                    None => {
+                        self.in_block_comment = true;
+                        break;
+                    }
+                }
+                continue;
+            }
+            let c = rest.chars().next().unwrap();
+            let len = if c == '"' || c == '\'' { quoted_len(rest, c) } else { c.len_utf8() };
+            stripped.push_str(&rest[..len]);
+            rest = &rest[len..];
+        }
+        stripped
+    }
+
+    /// Copies `text` to `output` with macros expanded. Macros in `hidden` are
+    /// being expanded already and are left alone, so that a self-referential
+    /// macro does not expand forever.
+    fn expand(&mut self, text: &str, line_no: usize, hidden: &mut Vec<String>, output: &mut String) {
+        let mut rest = text;
+        while !rest.is_empty() {
+            if self.in_block_comment {
+                match rest.find("*/") {
+                    Some(end) => {
+                        output.push_str(&rest[..end + 2]);
+                        rest = &rest[end + 2..];
+                        self.in_block_comment = false;
+                    }
+                    None => {
+                        output.push_str(rest);
+                        return;
+                    }
+                }
+                continue;
+            }
+            if rest.starts_with("//") {
+                output.push_str(rest);
+                return;
+            }
+            if rest.starts_with("/*") {
+                output.push_str("/*");
+                rest = &rest[2..];
+                self.in_block_comment = true;
+                continue;
+            }
+
+            let c = rest.chars().next().unwrap();
+            let len = if c == '"' || c == '\'' {
+                quoted_len(rest, c)
+            } else if c.is_ascii_digit() {
+                rest.find(|c: char| !is_ident_char(c)).unwrap_or(rest.len())
+            } else if is_ident_char(c) {
+                let len = rest.find(|c: char| !is_ident_char(c)).unwrap_or(rest.len());
+                let (ident, after) = rest.split_at(len);
+                if let Some(skipped) = self.expand_ident(ident, after, line_no, hidden, output) {
+                    rest = &after[skipped..];
+                    continue;
+                }
+                len
+            } else {
+                c.len_utf8()
+            };
+            output.push_str(&rest[..len]);
+            rest = &rest[len..];
+        }
+    }
+
+    /// Expands the identifier `ident` if it names a macro. Returns how much of
+    /// the text `after` it has consumed, or `None` to keep `ident` as it is.
+    fn expand_ident(&mut self, ident: &str, after: &str, line_no: usize, hidden: &mut Vec<String>, output: &mut String) -> Option<usize> {
+        if hidden.iter().any(|macro_name| macro_name == ident) {
+            return None;
+        }
+        match ident {
+            "__LINE__" => {
+                output.push_str(&line_no.to_string());
+                Some(0)
+            }
+            "starttime" | "stoptime" => {
+                let call_len = empty_call_len(after)?;
+                output.push_str(&format!("_sysy_{}({})", ident, line_no));
+                Some(call_len)
+            }
+            _ => {
+                let replacement = self.macros.get(ident)?.clone();
+                hidden.push(ident.to_string());
+                self.expand(&replacement, line_no, hidden, output);
+                hidden.pop();
+                Some(0)
+            }
+        }
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Returns the length of the string or character literal at the start of
+/// `text`, or of the rest of the line if the literal is unterminated.
+fn quoted_len(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return i + 1;
+        }
+    }
+    text.len()
+}
+
+/// Returns the length of `()` at the start of `text`, allowing whitespace.
+fn empty_call_len(text: &str) -> Option<usize> {
+    let open = text.len() - text.trim_start().len();
+    let text = text.trim_start().strip_prefix('(')?;
+    let close = text.len() - text.trim_start().len();
+    text.trim_start().strip_prefix(')')?;
+    Some(open + 1 + close + 1)
+}
@@ -0,0 +1,1539 @@
+use super::{env::{DeclType, SemaEnv}, eval::{convert_const, Evaluate}, flow, initializer::Flatten, symbol::{FieldInfo, FieldType, StructInfo, SymbolInfo}, tree, ty::Ty, SemaError};
+use crate::ast::*;
+use crate::span::Span;
+use std::collections::HashSet;
+use std::result::Result;
+
+pub trait Analyze<'ast> {
+    type Out;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError>;
+}
+
+impl<'ast> Analyze<'ast> for CompUnit {
+    type Out = Vec<tree::Item>;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        env.push_scope();
+        let mut new_decl = |ident: &'ast str, params_ty, ret_ty| {
+            env.new_func(tree::Func {
+                ident,
+                ret_ty,
+                params_ty,
+                is_variadic: false,
+                is_library: true,
+                is_called: false,
+                body: None,
+            })
+        };
+        let int_ptr = || Ty::Pointer(Box::new(Ty::Int));
+        new_decl("getint", vec![], Ty::Int);
+        new_decl("getch", vec![], Ty::Int);
+        new_decl("getarray", vec![int_ptr()], Ty::Int);
+        new_decl("putint", vec![Ty::Int], Ty::Void);
+        new_decl("putch", vec![Ty::Int], Ty::Void);
+        new_decl("putarray", vec![Ty::Int, int_ptr()], Ty::Void);
+        // `starttime()`/`stoptime()` are macros that the preprocessor expands
+        // into these, passing the line number.
+        new_decl("_sysy_starttime", vec![Ty::Int], Ty::Void);
+        new_decl("_sysy_stoptime", vec![Ty::Int], Ty::Void);
+        // `void putf(char fmt[], ...)`, where the call sites may pass more
+        // arguments after the format string.
+        let putf = new_decl("putf", vec![Ty::Pointer(Box::new(Ty::Char))], Ty::Void);
+        env.get_func_info_mut(putf).is_variadic = true;
+
+        // Global declarations and function signatures come first, so that a
+        // function can be called before its definition.
+        let mut items = vec![];
+        let mut defined_funcs = HashSet::new();
+        for comp_unit in &self.comp_unit_list {
+            match comp_unit {
+                SimpleCompUnit::Decl(decl) => {
+                    let decl_type = if is_static_decl(decl) { DeclType::Static } else { DeclType::Global };
+                    env.set_cur_decl_type(Some(decl_type));
+                    let var_def_list = decl.analyze(env)?;
+                    env.set_cur_decl_type(None);
+                    for var_def in var_def_list {
+                        // A definition takes the place of the extern
+                        // declaration it completes.
+                        items.retain(|item| !matches!(item, tree::Item::Var(old) if old.object == var_def.object));
+                        items.push(tree::Item::Var(var_def));
+                    }
+                }
+                SimpleCompUnit::FuncDecl(func_decl) => {
+                    let func = func_decl.analyze(env).map_err(|err| err.at(func_decl.span))?;
+                    items.push(tree::Item::FuncDecl(func));
+                }
+                SimpleCompUnit::FuncDef(func_def) => {
+                    if !defined_funcs.insert(func_def.ident.as_str()) {
+                        return Err(SemaError::SymbolDeclaredMoreThanOnce.at(func_def.span));
+                    }
+                    let func = analyze_func_decl(&func_def.ident, &func_def.func_type, &func_def.func_f_params, true, env)
+                        .map_err(|err| err.at(func_def.span))?;
+                    items.push(tree::Item::FuncDef(func));
+                }
+                SimpleCompUnit::Error => {}
+            }
+        }
+        for comp_unit in &self.comp_unit_list {
+            if let SimpleCompUnit::FuncDef(func_def) = comp_unit {
+                let body = func_def.analyze(env).map_err(|err| err.at(func_def.span))?;
+                let func = env.get_func(&func_def.ident).unwrap();
+                env.get_func_info_mut(func).body = Some(body);
+            }
+        }
+        env.pop_scope();
+        Ok(items)
+    }
+}
+
+impl<'ast> Analyze<'ast> for Decl {
+    type Out = Vec<tree::VarDef>;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::ConstDecl(const_decl) => {
+                const_decl.analyze(env)
+            },
+            Self::VarDecl(var_decl) => {
+                var_decl.analyze(env)
+            },
+            Self::EnumDecl(enum_decl) => {
+                enum_decl.analyze(env).map_err(|err| err.at(enum_decl.span))?;
+                Ok(vec![])
+            },
+            Self::StructDecl(struct_decl) => {
+                struct_decl.analyze(env).map_err(|err| err.at(struct_decl.span))?;
+                Ok(vec![])
+            },
+            Self::TypedefDecl(typedef_decl) => {
+                typedef_decl.analyze(env).map_err(|err| err.at(typedef_decl.span))?;
+                Ok(vec![])
+            },
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for ConstDecl {
+    type Out = Vec<tree::VarDef>;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        let mut var_def_list = vec![];
+        for const_def in &self.const_def_list {
+            var_def_list.extend(const_def.analyze(env).map_err(|err| err.at(const_def.span))?);
+        }
+        Ok(var_def_list)
+    }
+}
+
+impl<'ast> Analyze<'ast> for EnumDecl {
+    type Out = ();
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        let mut val = 0;
+        for (i, enumerator) in self.enumerator_list.iter().enumerate() {
+            // An enumerator without an initializer is one more than the
+            // previous one, and the first one defaults to 0.
+            val = match enumerator.const_exp.as_ref() {
+                Some(const_exp) => const_exp.evaluate(env).map_err(|err| err.at(enumerator.span))?,
+                None if i == 0 => 0,
+                None => val.wrapping_add(1),
+            };
+            if env.contains_symbol_in_cur_scope(&enumerator.ident) {
+                return Err(SemaError::SymbolDeclaredMoreThanOnce.at(enumerator.span));
+            }
+            env.new_symbol_const(&enumerator.ident, val);
+        }
+        Ok(())
+    }
+}
+
+impl<'ast> Analyze<'ast> for StructDecl {
+    type Out = ();
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        if env.contains_struct_in_cur_scope(&self.ident) {
+            return Err(SemaError::SymbolDeclaredMoreThanOnce);
+        }
+        let mut field_list: Vec<FieldInfo> = vec![];
+        let mut size = 0;
+        for field in &self.field_list {
+            if field_list.iter().any(|field_info| field_info.ident == field.ident) {
+                return Err(SemaError::SymbolDeclaredMoreThanOnce.at(field.span));
+            }
+            // A field takes whole words, which can't hold a pointer in the IR.
+            let ty = match resolve_ty(&field.b_type, env).map_err(|err| err.at(field.span))? {
+                Ty::Int => FieldType::Int,
+                Ty::Char => FieldType::Char,
+                Ty::Struct(struct_id) => FieldType::Struct(struct_id),
+                _ => return Err(SemaError::UnknownType.at(field.span)),
+            };
+            let dims = evaluate_dims(&field.dim_list, env).map_err(|err| err.at(field.span))?;
+            let field_size = get_field_type_size(ty, env) * dims.iter().product::<usize>();
+            field_list.push(FieldInfo { ident: &field.ident, ty, dims, offset: size });
+            size += field_size;
+        }
+        // The tag is only visible after the fields, so a struct can't
+        // contain itself.
+        env.new_struct(&self.ident, StructInfo { field_list, size });
+        Ok(())
+    }
+}
+
+impl<'ast> Analyze<'ast> for TypedefDecl {
+    type Out = ();
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        if let Some(enum_decl) = self.enum_decl.as_ref() {
+            enum_decl.analyze(env).map_err(|err| err.at(enum_decl.span))?;
+        }
+        if let Some(struct_decl) = self.struct_decl.as_ref() {
+            struct_decl.analyze(env).map_err(|err| err.at(struct_decl.span))?;
+        }
+        let b_type = resolve_alias(&self.b_type, env)?;
+        if env.contains_symbol_in_cur_scope(&self.ident) {
+            return Err(SemaError::SymbolDeclaredMoreThanOnce);
+        }
+        env.new_symbol_type_alias(&self.ident, b_type);
+        Ok(())
+    }
+}
+
+impl<'ast> Analyze<'ast> for ConstDef {
+    type Out = Option<tree::VarDef>; // `None` for a scalar, which is folded wherever it is used.
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        if !self.dim_list.is_empty() {
+            return analyze_const_array(self, env).map(Some);
+        }
+        let ty = resolve_ty(&self.b_type, env)?;
+        check_const_ty(&ty)?;
+        let const_val = convert_const(&ty, self.const_init_val.evaluate(env)?);
+        match env.get_cur_decl_type().unwrap() {
+            DeclType::Global => {
+                if env.contains_symbol_in_global_scope(&self.ident) {
+                    return Err(SemaError::SymbolDeclaredMoreThanOnce);
+                }
+                env.new_symbol_const_in_global_scope(&self.ident, const_val);
+            },
+            DeclType::Local | DeclType::Static => {
+                if env.contains_symbol_in_cur_scope(&self.ident) {
+                    return Err(SemaError::SymbolDeclaredMoreThanOnce);
+                }
+                env.new_symbol_const(&self.ident, const_val);
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<'ast> Analyze<'ast> for VarDecl {
+    type Out = Vec<tree::VarDef>;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        let mut var_def_list = vec![];
+        for var_def in &self.var_def_list {
+            let checked = if self.is_extern {
+                analyze_extern_var(var_def, env)
+            } else {
+                var_def.analyze(env)
+            };
+            var_def_list.push(checked.map_err(|err| err.at(var_def.span))?);
+        }
+        Ok(var_def_list)
+    }
+}
+
+impl<'ast> Analyze<'ast> for VarDef {
+    type Out = tree::VarDef;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        if !self.dim_list.is_empty() {
+            return analyze_var_array(self, env);
+        }
+        let ty = resolve_ty(&self.b_type, env)?;
+        if let Ty::Struct(_) = ty {
+            return analyze_struct_var(self, ty, env);
+        }
+        let decl_type = env.get_cur_decl_type().unwrap();
+        if env.contains_symbol_in_cur_scope(&self.ident) && extern_to_complete(&self.ident, &ty, decl_type, env).is_none() {
+            return Err(SemaError::SymbolDeclaredMoreThanOnce);
+        }
+        match decl_type {
+            DeclType::Global | DeclType::Static => {
+                let init = match self.init_val.as_ref() {
+                    // The address of another global is not a constant in the IR.
+                    Some(_) if matches!(ty, Ty::Pointer(_)) => {
+                        return Err(SemaError::InvalidInitializer);
+                    },
+                    Some(init_val) => {
+                        let val = init_val.evaluate(env).map_err(|err| global_init_error(decl_type, err))?;
+                        tree::Init::Const(vec![convert_const(&ty, val)])
+                    },
+                    None => tree::Init::None,
+                };
+                let object = new_object(&self.ident, ty, decl_type, false, env);
+                Ok(tree::VarDef { object, init })
+            },
+            DeclType::Local => {
+                // The variable is in scope in its own initializer.
+                let object = new_object(&self.ident, ty.clone(), decl_type, false, env);
+                let init = match self.init_val.as_ref() {
+                    Some(InitVal::Exp(exp)) => tree::Init::Exp(analyze_local_init(&ty, exp, env)?),
+                    Some(InitVal::List(_)) => return Err(SemaError::InvalidInitializer),
+                    None => tree::Init::None,
+                };
+                Ok(tree::VarDef { object, init })
+            }
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for InitVal {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::Exp(exp) => exp.analyze(env),
+            Self::List(_) => Err(SemaError::InvalidInitializer),
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for FuncDecl {
+    type Out = tree::FuncId;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        analyze_func_decl(&self.ident, &self.func_type, &self.func_f_params, false, env)
+    }
+}
+
+impl<'ast> Analyze<'ast> for FuncDef {
+    type Out = tree::FuncBody<'ast>;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        env.push_scope();
+
+        let ret_ty = resolve_ty(&self.func_type, env)?;
+        let returns_value = ret_ty != Ty::Void;
+        env.set_cur_ret_ty(Some(ret_ty));
+
+        let mut params = vec![];
+        if let Some(func_f_params) = self.func_f_params.as_ref() {
+            for func_f_param in &func_f_params.func_f_param_list {
+                let Some(ident) = func_f_param.ident.as_ref() else {
+                    params.push(None);
+                    continue;
+                };
+                if env.contains_symbol_in_cur_scope(ident) {
+                    return Err(SemaError::SymbolDeclaredMoreThanOnce.at(func_f_param.span));
+                }
+                let ty = resolve_param_ty(func_f_param, env)?;
+                let object = env.new_object(tree::Object { ident, ty, storage: tree::Storage::Param, is_const: false });
+                params.push(Some(object));
+            }
+        }
+
+        let mut block = self.block.analyze(env)?;
+
+        // `main` returns 0 when it runs off its end.
+        if returns_value && flow::falls_through(&block) {
+            if self.ident == "main" {
+                let zero = tree::Expr::new(tree::ExprKind::Int(0), Ty::Int);
+                block.stmt_list.push(tree::Stmt::Return(Some(zero)));
+            } else {
+                env.warn(SemaError::MissingReturn(self.ident.clone()).at(self.span));
+            }
+        }
+
+        env.set_cur_ret_ty(None);
+        env.pop_scope();
+
+        let undefined_label = env.take_labels().into_values()
+            .filter(|label| !label.defined)
+            .filter_map(|label| label.first_goto)
+            .min_by_key(|span| span.start);
+        if let Some(span) = undefined_label {
+            return Err(SemaError::UndefinedLabel.at(span));
+        }
+
+        Ok(tree::FuncBody { params, block, span: self.span })
+    }
+}
+
+impl<'ast> Analyze<'ast> for Block {
+    type Out = tree::Block<'ast>;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        let mut stmt_list = vec![];
+        for block_item in &self.block_item_list {
+            stmt_list.extend(block_item.analyze(env)?);
+        }
+        Ok(tree::Block { stmt_list })
+    }
+}
+
+impl<'ast> Analyze<'ast> for BlockItem {
+    type Out = Option<tree::Stmt<'ast>>; // `None` for a declaration that declares no object.
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::Stmt(stmt) => {
+                stmt.analyze(env).map(Some)
+            },
+            Self::Decl(decl) => {
+                analyze_local_decl(decl, env)
+            },
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for Stmt {
+    type Out = tree::Stmt<'ast>;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::OpenStmt(open_stmt) => {
+                open_stmt.analyze(env)
+            },
+            Self::ClosedStmt(closed_stmt) => {
+                closed_stmt.analyze(env)
+            },
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for OpenStmt {
+    type Out = tree::Stmt<'ast>;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::If(exp, stmt, span) => {
+                analyze_if(exp, stmt.as_ref(), None::<&Stmt>, env).map_err(|err| err.at(*span))
+            },
+            Self::IfElse(exp, then_stmt, else_stmt, span) => {
+                analyze_if(exp, then_stmt.as_ref(), Some(else_stmt.as_ref()), env).map_err(|err| err.at(*span))
+            },
+            Self::While(exp, stmt, span) => {
+                analyze_while(exp, stmt.as_ref(), env).map_err(|err| err.at(*span))
+            },
+            Self::For(for_init, exp, for_step, stmt, span) => {
+                analyze_for(for_init, exp, for_step, stmt.as_ref(), env).map_err(|err| err.at(*span))
+            },
+            Self::Labeled(ident, span, stmt) => {
+                analyze_label(ident, *span, env)?;
+                Ok(tree::Stmt::Labeled(ident, Box::new(stmt.analyze(env)?)))
+            },
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for ClosedStmt {
+    type Out = tree::Stmt<'ast>;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::SimpleStmt(simple_stmt) => {
+                simple_stmt.analyze(env)
+            },
+            Self::IfElse(exp, then_stmt, else_stmt, span) => {
+                analyze_if(exp, then_stmt.as_ref(), Some(else_stmt.as_ref()), env).map_err(|err| err.at(*span))
+            },
+            Self::While(exp, stmt, span) => {
+                analyze_while(exp, stmt.as_ref(), env).map_err(|err| err.at(*span))
+            },
+            Self::For(for_init, exp, for_step, stmt, span) => {
+                analyze_for(for_init, exp, for_step, stmt.as_ref(), env).map_err(|err| err.at(*span))
+            },
+            Self::DoWhile(stmt, exp, _) => {
+                env.enter_loop();
+                let body = stmt.analyze(env)?;
+                env.exit_loop();
+                let cond = exp.analyze(env)?.expect_int().map_err(|err| err.at(exp.span))?;
+                Ok(tree::Stmt::DoWhile(Box::new(body), cond))
+            },
+            Self::Switch(exp, switch_item_list, span) => {
+                analyze_switch(exp, switch_item_list, env).map_err(|err| err.at(*span))
+            },
+            Self::Labeled(ident, span, stmt) => {
+                analyze_label(ident, *span, env)?;
+                Ok(tree::Stmt::Labeled(ident, Box::new(stmt.analyze(env)?)))
+            },
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for ForInit {
+    type Out = Option<tree::Stmt<'ast>>;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::Decl(decl) => {
+                analyze_local_decl(decl, env)
+            },
+            Self::Step(for_step) => {
+                for_step.analyze(env).map(Some)
+            },
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for ForStep {
+    type Out = tree::Stmt<'ast>;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::Assign(l_val, exp, span) => {
+                analyze_assign(l_val, exp, env).map_err(|err| err.at(*span))
+            },
+            Self::Exp(exp) => {
+                Ok(tree::Stmt::Exp(Some(exp.analyze(env)?)))
+            },
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for SimpleStmt {
+    type Out = tree::Stmt<'ast>;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::Assign(l_val, exp, span) => {
+                analyze_assign(l_val, exp, env).map_err(|err| err.at(*span))
+            },
+            Self::Exp(exp, _) => {
+                let exp = match exp.as_ref() {
+                    Some(exp) => Some(exp.analyze(env)?),
+                    None => None,
+                };
+                Ok(tree::Stmt::Exp(exp))
+            },
+            Self::Block(block) => {
+                env.push_scope();
+                let block = block.analyze(env)?;
+                env.pop_scope();
+                Ok(tree::Stmt::Block(block))
+            },
+            Self::Break(span) => {
+                if !env.in_breakable() {
+                    return Err(SemaError::BreakOutsideLoop.at(*span));
+                }
+                Ok(tree::Stmt::Break)
+            },
+            Self::Continue(span) => {
+                if !env.in_loop() {
+                    return Err(SemaError::ContinueOutsideLoop.at(*span));
+                }
+                Ok(tree::Stmt::Continue)
+            },
+            Self::Goto(ident, span) => {
+                let label = env.get_label_mut(ident);
+                if !label.defined && label.first_goto.is_none() {
+                    label.first_goto = Some(*span);
+                }
+                Ok(tree::Stmt::Goto(ident))
+            },
+            Self::Return(exp, span) => {
+                let ret_ty = env.get_cur_ret_ty().unwrap().clone();
+                if ret_ty == Ty::Void {
+                    if exp.is_some() {
+                        return Err(SemaError::ReturnWithExpressionInVoidFunction.at(*span));
+                    }
+                    return Ok(tree::Stmt::Return(None));
+                }
+                let val = match exp.as_ref() {
+                    Some(exp) => {
+                        let val = exp.analyze(env)?;
+                        Some(check_store_value(&ret_ty, val).map_err(|err| err.at(exp.span))?)
+                    },
+                    None => None,
+                };
+                Ok(tree::Stmt::Return(val))
+            },
+            Self::Error => Ok(tree::Stmt::Exp(None)),
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for Exp {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        self.assign_exp.analyze(env).map_err(|err| err.at(self.span))
+    }
+}
+
+impl<'ast> Analyze<'ast> for AssignExp {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::CondExp(cond_exp) => {
+                cond_exp.analyze(env)
+            },
+            Self::CompoundAssign(l_val, assign_op, assign_exp, span) => {
+                let place = analyze_assign_place(l_val, env)?;
+                let rhs = assign_exp.analyze(env)?.expect_int().map_err(|err| err.at(*span))?;
+                let ty = match &place.ty {
+                    Ty::Pointer(_) => {
+                        if !matches!(assign_op, AssignOp::Add | AssignOp::Sub) {
+                            return Err(SemaError::TypeMismatch.at(*span));
+                        }
+                        env.expect_extended("Pointer arithmetic").map_err(|err| err.at(*span))?;
+                        place.ty.clone()
+                    },
+                    _ => Ty::Int,
+                };
+                Ok(tree::Expr::new(tree::ExprKind::CompoundAssign(Box::new(place), *assign_op, Box::new(rhs)), ty))
+            }
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for LVal {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::Var(var) => {
+                var.analyze(env)
+            },
+            Self::Deref(unary_exp, _) => {
+                let place = analyze_deref_place(unary_exp, env)?;
+                load_place(place)
+            },
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for VarRef {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        if let Some(&SymbolInfo::Const(val)) = env.get_symbol(&self.ident) {
+            if !self.index_list.is_empty() {
+                return Err(SemaError::SubscriptOnNonArray);
+            }
+            if !self.field_list.is_empty() {
+                return Err(SemaError::FieldAccessOnNonStruct);
+            }
+            return Ok(tree::Expr::new(tree::ExprKind::Int(val), Ty::Int));
+        }
+        if !self.field_list.is_empty() {
+            let (place, field_info, indexed) = analyze_field_place(self, env)?;
+            return match (field_info.ty, field_info.dims.len() - indexed) {
+                (FieldType::Struct(_), 0) => Err(SemaError::UsingStructAsValue),
+                (ty, 0) => Ok(tree::Expr::new(tree::ExprKind::Load(Box::new(place)), ty.to_ty())),
+                // Only a one-dimensional array field decays to a pointer, as
+                // an `*i32` can't stand for a pointer to an array.
+                (ty @ (FieldType::Int | FieldType::Char), 1) => {
+                    Ok(tree::Expr::new(tree::ExprKind::Decay(Box::new(place)), Ty::Pointer(Box::new(ty.to_ty()))))
+                },
+                _ => Err(SemaError::UsingArrayAsValue),
+            };
+        }
+        let place = analyze_var_place(self, env)?;
+        load_place(place)
+    }
+}
+
+impl<'ast> Analyze<'ast> for UnaryExp {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::PrimaryExp(primary_exp) => {
+                primary_exp.analyze(env)
+            },
+            Self::FuncCall(ident, func_r_params, span) => {
+                let mut args = vec![];
+                if let Some(func_r_params) = func_r_params {
+                    for func_r_param in &func_r_params.exp_list {
+                        args.push(func_r_param.analyze(env)?.expect_value()?);
+                    }
+                }
+                let Some(func) = env.get_func(ident) else {
+                    return Err(SemaError::FunctionUndeclared.at(*span));
+                };
+                let func_info = env.get_func_info(func);
+                let expected = func_info.params_ty.len();
+                if args.len() > expected && !func_info.is_variadic {
+                    return Err(SemaError::TooManyArguments(ident.clone(), expected, args.len()).at(*span));
+                }
+                if args.len() < expected {
+                    return Err(SemaError::TooFewArguments(ident.clone(), expected, args.len()).at(*span));
+                }
+                // The extra arguments of a variadic function are only checked
+                // to have a value.
+                let args = args
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, arg)| match func_info.params_ty.get(i) {
+                        Some(ty) if func_info.is_library => check_library_arg_value(ty, arg),
+                        Some(ty) => check_store_value(ty, arg),
+                        None => Ok(arg),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|err| err.at(*span))?;
+                let ret_ty = func_info.ret_ty.clone();
+                env.get_func_info_mut(func).is_called = true;
+                Ok(tree::Expr::new(tree::ExprKind::Call(func, args), ret_ty))
+            },
+            Self::UnaryExp(op, unary_exp, span) => {
+                let exp = unary_exp.analyze(env)?.expect_int().map_err(|err| err.at(*span))?;
+                let op = match op {
+                    UnaryOp::Plus => return Ok(exp),
+                    UnaryOp::Minus => tree::UnaryOp::Minus,
+                    UnaryOp::Not => tree::UnaryOp::Not,
+                    UnaryOp::BitNot => tree::UnaryOp::BitNot,
+                };
+                Ok(tree::Expr::new(tree::ExprKind::Unary(op, Box::new(exp)), Ty::Int))
+            },
+            Self::AddrOf(l_val, span) => {
+                env.expect_extended("Taking an address").map_err(|err| err.at(*span))?;
+                let place = analyze_l_val_place(l_val, env).map_err(|err| err.at(l_val.span()))?;
+                let ty = Ty::Pointer(Box::new(place.ty.clone()));
+                Ok(tree::Expr::new(tree::ExprKind::AddrOf(Box::new(place)), ty))
+            },
+            Self::PrefixIncDec(op, l_val, span) => {
+                let place = analyze_inc_dec_place(l_val, env).map_err(|err| err.at(*span))?;
+                let ty = inc_dec_ty(&place.ty);
+                Ok(tree::Expr::new(tree::ExprKind::PrefixIncDec(*op, Box::new(place)), ty))
+            },
+            Self::PostfixIncDec(l_val, op, span) => {
+                let place = analyze_inc_dec_place(l_val, env).map_err(|err| err.at(*span))?;
+                let ty = inc_dec_ty(&place.ty);
+                Ok(tree::Expr::new(tree::ExprKind::PostfixIncDec(Box::new(place), *op), ty))
+            }
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for PrimaryExp {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::Exp(exp) => {
+                exp.analyze(env)
+            },
+            Self::LVal(l_val) => {
+                l_val.analyze(env).map_err(|err| err.at(l_val.span()))
+            },
+            Self::Number(num) => {
+                Ok(tree::Expr::new(tree::ExprKind::Int(*num), Ty::Int))
+            },
+            Self::StringLiteral(string) => {
+                Ok(tree::Expr::new(tree::ExprKind::String(string.clone()), Ty::Pointer(Box::new(Ty::Char))))
+            }
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for MulExp {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::UnaryExp(unary_exp) => unary_exp.analyze(env),
+            Self::Mul(mul_exp, unary_exp, span) => analyze_binary(tree::BinaryOp::Mul, mul_exp.as_ref(), unary_exp.as_ref(), *span, env),
+            Self::Div(mul_exp, unary_exp, span) => analyze_binary(tree::BinaryOp::Div, mul_exp.as_ref(), unary_exp.as_ref(), *span, env),
+            Self::Mod(mul_exp, unary_exp, span) => analyze_binary(tree::BinaryOp::Mod, mul_exp.as_ref(), unary_exp.as_ref(), *span, env),
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for AddExp {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::MulExp(mul_exp) => {
+                mul_exp.analyze(env)
+            },
+            Self::Add(add_exp, mul_exp, span) => {
+                let lhs = add_exp.analyze(env)?;
+                let rhs = mul_exp.analyze(env)?;
+                match (&lhs.ty, &rhs.ty) {
+                    (Ty::Pointer(base), Ty::Int | Ty::Char) | (Ty::Int | Ty::Char, Ty::Pointer(base)) => {
+                        env.expect_extended("Pointer arithmetic").map_err(|err| err.at(*span))?;
+                        let ty = Ty::Pointer(base.clone());
+                        Ok(tree::Expr::new(tree::ExprKind::PtrAdd(Box::new(lhs), Box::new(rhs)), ty))
+                    },
+                    _ => {
+                        let (lhs, rhs) = (lhs.expect_int().map_err(|err| err.at(*span))?, rhs.expect_int().map_err(|err| err.at(*span))?);
+                        Ok(tree::Expr::new(tree::ExprKind::Binary(tree::BinaryOp::Add, Box::new(lhs), Box::new(rhs)), Ty::Int))
+                    },
+                }
+            },
+            Self::Sub(add_exp, mul_exp, span) => {
+                let lhs = add_exp.analyze(env)?;
+                let rhs = mul_exp.analyze(env)?;
+                match (&lhs.ty, &rhs.ty) {
+                    (Ty::Pointer(_), Ty::Int | Ty::Char) => {
+                        env.expect_extended("Pointer arithmetic").map_err(|err| err.at(*span))?;
+                        let ty = lhs.ty.clone();
+                        Ok(tree::Expr::new(tree::ExprKind::PtrSub(Box::new(lhs), Box::new(rhs)), ty))
+                    },
+                    (Ty::Pointer(_), Ty::Pointer(_)) => Err(SemaError::PointerDifference.at(*span)),
+                    _ => {
+                        let (lhs, rhs) = (lhs.expect_int().map_err(|err| err.at(*span))?, rhs.expect_int().map_err(|err| err.at(*span))?);
+                        Ok(tree::Expr::new(tree::ExprKind::Binary(tree::BinaryOp::Sub, Box::new(lhs), Box::new(rhs)), Ty::Int))
+                    },
+                }
+            }
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for ShiftExp {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::AddExp(add_exp) => add_exp.analyze(env),
+            Self::Shl(shift_exp, add_exp, span) => analyze_binary(tree::BinaryOp::Shl, shift_exp.as_ref(), add_exp.as_ref(), *span, env),
+            Self::Sar(shift_exp, add_exp, span) => analyze_binary(tree::BinaryOp::Sar, shift_exp.as_ref(), add_exp.as_ref(), *span, env),
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for RelExp {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::ShiftExp(shift_exp) => shift_exp.analyze(env),
+            Self::Lt(rel_exp, shift_exp, span) => analyze_binary(tree::BinaryOp::Lt, rel_exp.as_ref(), shift_exp.as_ref(), *span, env),
+            Self::Gt(rel_exp, shift_exp, span) => analyze_binary(tree::BinaryOp::Gt, rel_exp.as_ref(), shift_exp.as_ref(), *span, env),
+            Self::Le(rel_exp, shift_exp, span) => analyze_binary(tree::BinaryOp::Le, rel_exp.as_ref(), shift_exp.as_ref(), *span, env),
+            Self::Ge(rel_exp, shift_exp, span) => analyze_binary(tree::BinaryOp::Ge, rel_exp.as_ref(), shift_exp.as_ref(), *span, env),
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for EqExp {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::RelExp(rel_exp) => rel_exp.analyze(env),
+            Self::Eq(eq_exp, rel_exp, span) => analyze_binary(tree::BinaryOp::Eq, eq_exp.as_ref(), rel_exp.as_ref(), *span, env),
+            Self::Ne(eq_exp, rel_exp, span) => analyze_binary(tree::BinaryOp::Ne, eq_exp.as_ref(), rel_exp.as_ref(), *span, env),
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for BitAndExp {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::EqExp(eq_exp) => eq_exp.analyze(env),
+            Self::BitAnd(bit_and_exp, eq_exp, span) => analyze_binary(tree::BinaryOp::BitAnd, bit_and_exp.as_ref(), eq_exp.as_ref(), *span, env),
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for BitXorExp {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::BitAndExp(bit_and_exp) => bit_and_exp.analyze(env),
+            Self::BitXor(bit_xor_exp, bit_and_exp, span) => analyze_binary(tree::BinaryOp::BitXor, bit_xor_exp.as_ref(), bit_and_exp.as_ref(), *span, env),
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for BitOrExp {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::BitXorExp(bit_xor_exp) => bit_xor_exp.analyze(env),
+            Self::BitOr(bit_or_exp, bit_xor_exp, span) => analyze_binary(tree::BinaryOp::BitOr, bit_or_exp.as_ref(), bit_xor_exp.as_ref(), *span, env),
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for LAndExp {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::BitOrExp(bit_or_exp) => {
+                bit_or_exp.analyze(env)
+            },
+            Self::And(l_and_exp, bit_or_exp, span) => {
+                let lhs = l_and_exp.analyze(env)?.expect_int().map_err(|err| err.at(*span))?;
+                let rhs = bit_or_exp.analyze(env)?.expect_int().map_err(|err| err.at(*span))?;
+                Ok(tree::Expr::new(tree::ExprKind::And(Box::new(lhs), Box::new(rhs)), Ty::Int))
+            }
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for LOrExp {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::LAndExp(l_and_exp) => {
+                l_and_exp.analyze(env)
+            },
+            Self::Or(l_or_exp, l_and_exp, span) => {
+                let lhs = l_or_exp.analyze(env)?.expect_int().map_err(|err| err.at(*span))?;
+                let rhs = l_and_exp.analyze(env)?.expect_int().map_err(|err| err.at(*span))?;
+                Ok(tree::Expr::new(tree::ExprKind::Or(Box::new(lhs), Box::new(rhs)), Ty::Int))
+            }
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for CondExp {
+    type Out = tree::Expr;
+
+    fn analyze(&'ast self, env: &mut SemaEnv<'ast>) -> Result<Self::Out, SemaError> {
+        match self {
+            Self::LOrExp(l_or_exp) => {
+                l_or_exp.analyze(env)
+            },
+            Self::Cond(l_or_exp, exp, cond_exp, span) => {
+                let cond = l_or_exp.analyze(env)?.expect_int().map_err(|err| err.at(*span))?;
+                let true_val = exp.analyze(env)?.expect_int().map_err(|err| err.at(*span))?;
+                let false_val = cond_exp.analyze(env)?.expect_int().map_err(|err| err.at(*span))?;
+                Ok(tree::Expr::new(tree::ExprKind::Cond(Box::new(cond), Box::new(true_val), Box::new(false_val)), Ty::Int))
+            }
+        }
+    }
+}
+
+/// Checks the signature of a function. A definition replaces an earlier
+/// prototype of the same function, and any other redeclaration must agree
+/// with the existing signature.
+fn analyze_func_decl<'ast>(
+    ident: &'ast str,
+    func_type: &'ast BType,
+    func_f_params: &'ast Option<FuncFParams>,
+    is_def: bool,
+    env: &mut SemaEnv<'ast>,
+) -> Result<tree::FuncId, SemaError> {
+    let ret_ty = resolve_ty(func_type, env)?;
+    if let Ty::Struct(_) = ret_ty {
+        return Err(SemaError::UsingStructAsValue);
+    }
+    let mut params_ty = vec![];
+    if let Some(func_f_params) = func_f_params.as_ref() {
+        for func_f_param in &func_f_params.func_f_param_list {
+            params_ty.push(resolve_param_ty(func_f_param, env).map_err(|err| err.at(func_f_param.span))?);
+        }
+    }
+    if let Some(func) = env.get_func(ident) {
+        let func_info = env.get_func_info(func);
+        // The runtime library is only declared in the IR, where a `char` is
+        // an `int`.
+        let is_same = if func_info.is_library {
+            ret_ty.same_repr(&func_info.ret_ty)
+                && params_ty.len() == func_info.params_ty.len()
+                && params_ty.iter().zip(&func_info.params_ty).all(|(a, b)| a.same_repr(b))
+        } else {
+            ret_ty == func_info.ret_ty && params_ty == func_info.params_ty
+        };
+        if !is_same {
+            return Err(SemaError::ConflictingFunctionDeclaration);
+        }
+        if is_def {
+            let func_info = env.get_func_info_mut(func);
+            func_info.ret_ty = ret_ty;
+            func_info.params_ty = params_ty;
+            func_info.is_variadic = false;
+            func_info.is_library = false;
+        }
+        return Ok(func);
+    } else if env.contains_symbol_in_global_scope(ident) {
+        return Err(SemaError::SymbolDeclaredMoreThanOnce);
+    }
+    Ok(env.new_func(tree::Func {
+        ident,
+        ret_ty,
+        params_ty,
+        is_variadic: false,
+        is_library: false,
+        is_called: false,
+        body: None,
+    }))
+}
+
+/// Checks that `val` can be stored into an object of type `ty`. A pointer can
+/// only take a pointer to the same type.
+fn check_store_value(ty: &Ty, val: tree::Expr) -> Result<tree::Expr, SemaError> {
+    match ty {
+        Ty::Pointer(base) => match &val.ty {
+            Ty::Pointer(pointee) if pointee == base => Ok(val),
+            Ty::Void => Err(SemaError::UsingVoidValue),
+            _ => Err(SemaError::TypeMismatch),
+        },
+        Ty::Array(..) => Err(SemaError::UsingArrayAsValue),
+        Ty::Struct(_) => Err(SemaError::UsingStructAsValue),
+        Ty::Int | Ty::Char | Ty::Void => val.expect_int(),
+    }
+}
+
+/// Checks that `val` can be passed to the runtime library as an argument of
+/// type `ty`. The runtime reads a `char *` as packed bytes rather than as one
+/// `char` per word, so only a string literal, which can be laid out that way,
+/// may be passed as one.
+fn check_library_arg_value(ty: &Ty, val: tree::Expr) -> Result<tree::Expr, SemaError> {
+    match ty {
+        Ty::Pointer(base) if **base == Ty::Char => match val.kind {
+            tree::ExprKind::String(string) => Ok(tree::Expr::new(tree::ExprKind::PackedString(string), val.ty)),
+            _ => Err(SemaError::NonLiteralRuntimeString),
+        },
+        _ => check_store_value(ty, val),
+    }
+}
+
+/// Looks up the type that a type name stands for. Any other type is returned
+/// as it is.
+fn resolve_alias<'ast>(b_type: &'ast BType, env: &SemaEnv<'ast>) -> Result<&'ast BType, SemaError> {
+    match b_type {
+        BType::Alias(ident) => match env.get_symbol(ident) {
+            Some(SymbolInfo::TypeAlias(b_type)) => Ok(b_type),
+            _ => Err(SemaError::UnknownType),
+        },
+        _ => Ok(b_type),
+    }
+}
+
+/// Resolves `b_type` to the type it stands for. An enumeration is an `int`.
+fn resolve_ty<'ast>(b_type: &'ast BType, env: &SemaEnv<'ast>) -> Result<Ty, SemaError> {
+    match resolve_alias(b_type, env)? {
+        BType::Char => Ok(Ty::Char),
+        BType::Void => Ok(Ty::Void),
+        BType::Struct(tag) => env.get_struct_id(tag).map(Ty::Struct).ok_or(SemaError::UnknownType),
+        // Nothing could be done with what a `void *` points to.
+        BType::Pointer(base) => {
+            env.expect_extended("A pointer type")?;
+            match resolve_ty(base, env)? {
+                Ty::Void => Err(SemaError::UnknownType),
+                ty => Ok(Ty::Pointer(Box::new(ty))),
+            }
+        },
+        _ => Ok(Ty::Int),
+    }
+}
+
+/// Resolves the type of a parameter, where an array is passed as a pointer
+/// to its first element. A struct can't be passed by value.
+fn resolve_param_ty<'ast>(func_f_param: &'ast FuncFParam, env: &SemaEnv<'ast>) -> Result<Ty, SemaError> {
+    let ty = resolve_ty(&func_f_param.b_type, env)?;
+    match func_f_param.dim_list.as_ref() {
+        Some(dim_list) => Ok(Ty::Pointer(Box::new(ty.with_dims(&evaluate_dims(dim_list, env)?)))),
+        None if matches!(ty, Ty::Struct(_)) => Err(SemaError::UsingStructAsValue),
+        None => Ok(ty),
+    }
+}
+
+/// Constants are folded into integers, so they can't be structs or pointers.
+fn check_const_ty(ty: &Ty) -> Result<(), SemaError> {
+    match ty {
+        Ty::Struct(_) => Err(SemaError::UsingStructAsValue),
+        Ty::Pointer(_) => Err(SemaError::TypeMismatch),
+        _ => Ok(()),
+    }
+}
+
+// In words.
+fn get_field_type_size(ty: FieldType, env: &SemaEnv) -> usize {
+    match ty {
+        FieldType::Struct(struct_id) => env.get_struct(struct_id).size,
+        FieldType::Int | FieldType::Char => 1,
+    }
+}
+
+/// A non-constant initializer of a static variable is reported as such, and
+/// not as one of a const symbol.
+fn global_init_error(decl_type: DeclType, err: SemaError) -> SemaError {
+    match err {
+        SemaError::Located(err, span) => global_init_error(decl_type, *err).at(span),
+        SemaError::InitializeConstWithVariable | SemaError::InitializeConstWithFunctionCall | SemaError::UseFunctionAsVariable
+            if matches!(decl_type, DeclType::Static) =>
+        {
+            SemaError::NonConstStaticInitializer
+        },
+        err => err,
+    }
+}
+
+fn is_static_decl(decl: &Decl) -> bool {
+    match decl {
+        Decl::ConstDecl(const_decl) => const_decl.is_static,
+        Decl::VarDecl(var_decl) => var_decl.is_static,
+        Decl::EnumDecl(_) | Decl::StructDecl(_) | Decl::TypedefDecl(_) => false,
+    }
+}
+
+/// Analyzes a declaration in a function, which is `None` if it declares no
+/// object.
+fn analyze_local_decl<'ast>(decl: &'ast Decl, env: &mut SemaEnv<'ast>) -> Result<Option<tree::Stmt<'ast>>, SemaError> {
+    let decl_type = if is_static_decl(decl) { DeclType::Static } else { DeclType::Local };
+    env.set_cur_decl_type(Some(decl_type));
+    let var_def_list = decl.analyze(env)?;
+    env.set_cur_decl_type(None);
+    if var_def_list.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(tree::Stmt::Decl(var_def_list)))
+}
+
+fn new_object<'ast>(ident: &'ast str, ty: Ty, decl_type: DeclType, is_const: bool, env: &mut SemaEnv<'ast>) -> tree::ObjectId {
+    if let Some(object) = extern_to_complete(ident, &ty, decl_type, env) {
+        env.get_object_mut(object).storage = tree::Storage::Global;
+        return object;
+    }
+    let storage = match decl_type {
+        DeclType::Global => tree::Storage::Global,
+        DeclType::Static => tree::Storage::Static,
+        DeclType::Local => tree::Storage::Local,
+    };
+    env.new_object(tree::Object { ident, ty, storage, is_const })
+}
+
+/// A global definition may follow an extern declaration of the same type, and
+/// then defines its object.
+fn extern_to_complete<'ast>(ident: &'ast str, ty: &Ty, decl_type: DeclType, env: &SemaEnv<'ast>) -> Option<tree::ObjectId> {
+    match (decl_type, env.get_symbol(ident)) {
+        (DeclType::Global, Some(SymbolInfo::Object(object))) => {
+            let object_info = env.get_object(*object);
+            (object_info.storage == tree::Storage::Extern && object_info.ty == *ty).then_some(*object)
+        },
+        _ => None,
+    }
+}
+
+fn evaluate_dims<'ast>(dim_list: &'ast [ConstExp], env: &SemaEnv<'ast>) -> Result<Vec<usize>, SemaError> {
+    let mut dims = vec![];
+    for const_exp in dim_list {
+        let dim = const_exp.evaluate(env)?;
+        if dim <= 0 {
+            return Err(SemaError::InvalidArraySize);
+        }
+        dims.push(dim as usize);
+    }
+    Ok(dims)
+}
+
+/// Folds the elements of a constant initializer list, converted to `elem_ty`.
+fn evaluate_init_list<'ast, T: Flatten>(init: &'ast T, dims: &[usize], elem_ty: &Ty, env: &SemaEnv<'ast>) -> Result<Vec<i32>, SemaError> {
+    if let Some(string) = init.as_string_literal() {
+        return string_init_list(string, dims, elem_ty);
+    }
+    let mut vals = vec![];
+    for elem in init.flatten(dims)? {
+        vals.push(match elem {
+            Some(exp) => convert_const(elem_ty, exp.evaluate(env)?),
+            None => 0,
+        });
+    }
+    Ok(vals)
+}
+
+/// Lays out a string literal that initializes a `char` array of shape `dims`
+/// as its bytes followed by zeros. The NUL is left out if the array is just
+/// long enough for the other bytes.
+fn string_init_list(string: &[u8], dims: &[usize], elem_ty: &Ty) -> Result<Vec<i32>, SemaError> {
+    if *elem_ty != Ty::Char || dims.len() != 1 || string.len() > dims[0] {
+        return Err(SemaError::InvalidInitializer);
+    }
+    let mut vals: Vec<i32> = string.iter().map(|&byte| byte as i32).collect();
+    vals.resize(dims[0], 0);
+    Ok(vals)
+}
+
+/// Resolves the object that `var` designates before any field accesses, which
+/// may be an array element or a sub-array when only some of the dimensions
+/// are indexed.
+fn analyze_var_place<'ast>(var: &'ast VarRef, env: &mut SemaEnv<'ast>) -> Result<tree::Place, SemaError> {
+    let object = match env.get_symbol(&var.ident) {
+        Some(SymbolInfo::Object(object)) => *object,
+        Some(SymbolInfo::Const(_)) => return Err(SemaError::AddressOfConst),
+        Some(SymbolInfo::Function(_)) => return Err(SemaError::UseFunctionAsVariable),
+        Some(SymbolInfo::TypeAlias(_)) => return Err(SemaError::UseTypeAsVariable),
+        None => return Err(SemaError::SymbolUndeclared),
+    };
+    let mut place = tree::Place { kind: tree::PlaceKind::Object(object), ty: env.get_object(object).ty.clone() };
+    for index in &var.index_list {
+        place = match place.ty.clone() {
+            Ty::Array(elem, _) => {
+                let index = index.analyze(env)?.expect_int()?;
+                tree::Place { kind: tree::PlaceKind::Index(Box::new(place), Box::new(index)), ty: *elem }
+            },
+            Ty::Pointer(base) => {
+                let index = index.analyze(env)?.expect_int()?;
+                tree::Place { kind: tree::PlaceKind::PtrIndex(Box::new(place), Box::new(index)), ty: *base }
+            },
+            _ => return Err(SemaError::SubscriptOnNonArray),
+        };
+    }
+    Ok(place)
+}
+
+/// Resolves the object that a pointer points to.
+fn analyze_deref_place<'ast>(unary_exp: &'ast UnaryExp, env: &mut SemaEnv<'ast>) -> Result<tree::Place, SemaError> {
+    env.expect_extended("Dereferencing")?;
+    let val = unary_exp.analyze(env)?;
+    match val.ty.clone() {
+        Ty::Pointer(base) => Ok(tree::Place { kind: tree::PlaceKind::Deref(Box::new(val)), ty: *base }),
+        Ty::Void => Err(SemaError::UsingVoidValue),
+        _ => Err(SemaError::DerefNonPointer),
+    }
+}
+
+/// Resolves the object that `l_val` designates. A field must be a scalar,
+/// since its address is an `*i32` whatever its type is.
+fn analyze_l_val_place<'ast>(l_val: &'ast LVal, env: &mut SemaEnv<'ast>) -> Result<tree::Place, SemaError> {
+    match l_val {
+        LVal::Var(var) if !var.field_list.is_empty() => {
+            let (place, field_info, indexed) = analyze_field_place(var, env)?;
+            match (field_info.ty, field_info.dims.len() - indexed) {
+                (FieldType::Struct(_), 0) => Err(SemaError::UsingStructAsValue),
+                (_, 0) => Ok(place),
+                _ => Err(SemaError::UsingArrayAsValue),
+            }
+        },
+        LVal::Var(var) => analyze_var_place(var, env),
+        LVal::Deref(unary_exp, _) => analyze_deref_place(unary_exp, env),
+    }
+}
+
+/// Reads the object as a value. An array decays to a pointer to its first
+/// element.
+fn load_place(place: tree::Place) -> Result<tree::Expr, SemaError> {
+    match place.ty.clone() {
+        ty @ (Ty::Int | Ty::Char | Ty::Pointer(_)) => Ok(tree::Expr::new(tree::ExprKind::Load(Box::new(place)), ty)),
+        Ty::Array(elem, _) => Ok(tree::Expr::new(tree::ExprKind::Decay(Box::new(place)), Ty::Pointer(elem))),
+        Ty::Struct(_) => Err(SemaError::UsingStructAsValue),
+        Ty::Void => Err(SemaError::UsingVoidValue),
+    }
+}
+
+/// Resolves the field that `var` designates. Also returns the field and how
+/// many of its dimensions are indexed.
+fn analyze_field_place<'ast>(var: &'ast VarRef, env: &mut SemaEnv<'ast>) -> Result<(tree::Place, FieldInfo<'ast>, usize), SemaError> {
+    // The fields belong to a single struct, not to an array of them.
+    let base = analyze_var_place(var, env)?;
+    let Ty::Struct(struct_id) = base.ty else {
+        return Err(SemaError::FieldAccessOnNonStruct);
+    };
+
+    let mut struct_id = Some(struct_id);
+    let mut field = None;
+    let mut steps = vec![];
+    for field_access in &var.field_list {
+        let Some(cur_struct_id) = struct_id else {
+            return Err(SemaError::FieldAccessOnNonStruct.at(field_access.span));
+        };
+        let field_info = env.get_struct(cur_struct_id).get_field(&field_access.ident).cloned()
+            .ok_or(SemaError::UnknownField.at(field_access.span))?;
+        if field_access.index_list.len() > field_info.dims.len() {
+            return Err(SemaError::SubscriptOnNonArray.at(field_access.span));
+        }
+        // The field is a flat run of words, so an array index is scaled by
+        // the size of what it selects.
+        let elem_size = get_field_type_size(field_info.ty, env);
+        let mut index_list = vec![];
+        for (i, index) in field_access.index_list.iter().enumerate() {
+            let stride = field_info.dims[i + 1..].iter().product::<usize>() * elem_size;
+            index_list.push((index.analyze(env)?.expect_int()?, stride));
+        }
+        steps.push(tree::FieldStep { offset: field_info.offset, index_list });
+        let indexed = field_access.index_list.len();
+        struct_id = match field_info.ty {
+            FieldType::Struct(struct_id) if indexed == field_info.dims.len() => Some(struct_id),
+            _ => None,
+        };
+        field = Some((field_info, indexed));
+    }
+    let (field_info, indexed) = field.unwrap();
+    let ty = field_info.ty.to_ty().with_dims(&field_info.dims[indexed..]);
+    let place = tree::Place { kind: tree::PlaceKind::Field(Box::new(base), steps), ty };
+    Ok((place, field_info, indexed))
+}
+
+/// Declares a global variable defined in another translation unit.
+fn analyze_extern_var<'ast>(var_def: &'ast VarDef, env: &mut SemaEnv<'ast>) -> Result<tree::VarDef, SemaError> {
+    if var_def.init_val.is_some() {
+        return Err(SemaError::InvalidInitializer);
+    }
+    let dims = evaluate_dims(&var_def.dim_list, env)?;
+    let ty = resolve_ty(&var_def.b_type, env)?.with_dims(&dims);
+    if env.contains_symbol_in_cur_scope(&var_def.ident) {
+        return Err(SemaError::SymbolDeclaredMoreThanOnce);
+    }
+    let object = env.new_object(tree::Object { ident: &var_def.ident, ty, storage: tree::Storage::Extern, is_const: false });
+    Ok(tree::VarDef { object, init: tree::Init::None })
+}
+
+/// Declares a struct variable or an array of structs. Those can't have
+/// initializers, so a global one is zero-initialized.
+fn analyze_struct_var<'ast>(var_def: &'ast VarDef, ty: Ty, env: &mut SemaEnv<'ast>) -> Result<tree::VarDef, SemaError> {
+    if var_def.init_val.is_some() {
+        return Err(SemaError::InvalidInitializer);
+    }
+    let dims = evaluate_dims(&var_def.dim_list, env)?;
+    let ty = ty.with_dims(&dims);
+    let decl_type = env.get_cur_decl_type().unwrap();
+    if env.contains_symbol_in_cur_scope(&var_def.ident) && extern_to_complete(&var_def.ident, &ty, decl_type, env).is_none() {
+        return Err(SemaError::SymbolDeclaredMoreThanOnce);
+    }
+    let object = new_object(&var_def.ident, ty, decl_type, false, env);
+    Ok(tree::VarDef { object, init: tree::Init::None })
+}
+
+fn analyze_const_array<'ast>(const_def: &'ast ConstDef, env: &mut SemaEnv<'ast>) -> Result<tree::VarDef, SemaError> {
+    let dims = evaluate_dims(&const_def.dim_list, env)?;
+    let elem_ty = resolve_ty(&const_def.b_type, env)?;
+    check_const_ty(&elem_ty)?;
+    let vals = evaluate_init_list(const_def.const_init_val.as_ref(), &dims, &elem_ty, env)?;
+    let ty = elem_ty.with_dims(&dims);
+    let decl_type = env.get_cur_decl_type().unwrap();
+    if env.contains_symbol_in_cur_scope(&const_def.ident) {
+        return Err(SemaError::SymbolDeclaredMoreThanOnce);
+    }
+    let object = new_object(&const_def.ident, ty, decl_type, true, env);
+    Ok(tree::VarDef { object, init: tree::Init::Const(vals) })
+}
+
+fn analyze_var_array<'ast>(var_def: &'ast VarDef, env: &mut SemaEnv<'ast>) -> Result<tree::VarDef, SemaError> {
+    let elem_ty = resolve_ty(&var_def.b_type, env)?;
+    if let Ty::Struct(_) = elem_ty {
+        return analyze_struct_var(var_def, elem_ty, env);
+    }
+    // The elements left out of an initializer list are zeros, which can't be
+    // pointers in the IR.
+    if matches!(elem_ty, Ty::Pointer(_)) && var_def.init_val.is_some() {
+        return Err(SemaError::InvalidInitializer);
+    }
+    let dims = evaluate_dims(&var_def.dim_list, env)?;
+    let ty = elem_ty.clone().with_dims(&dims);
+    let decl_type = env.get_cur_decl_type().unwrap();
+    if env.contains_symbol_in_cur_scope(&var_def.ident) && extern_to_complete(&var_def.ident, &ty, decl_type, env).is_none() {
+        return Err(SemaError::SymbolDeclaredMoreThanOnce);
+    }
+    match decl_type {
+        DeclType::Global | DeclType::Static => {
+            let init = match var_def.init_val.as_ref() {
+                Some(init_val) => {
+                    let vals = evaluate_init_list(init_val, &dims, &elem_ty, env).map_err(|err| global_init_error(decl_type, err))?;
+                    tree::Init::Const(vals)
+                },
+                None => tree::Init::None,
+            };
+            let object = new_object(&var_def.ident, ty, decl_type, false, env);
+            Ok(tree::VarDef { object, init })
+        },
+        DeclType::Local => {
+            let object = new_object(&var_def.ident, ty, decl_type, false, env);
+            let init = match var_def.init_val.as_ref() {
+                Some(init_val) => match init_val.as_string_literal() {
+                    Some(string) => {
+                        let vals = string_init_list(string, &dims, &elem_ty)?;
+                        tree::Init::List(vals.into_iter().map(|val| Some(tree::Expr::new(tree::ExprKind::Int(val), Ty::Int))).collect())
+                    },
+                    None => {
+                        let mut elems = vec![];
+                        for elem in init_val.flatten(&dims)? {
+                            elems.push(match elem {
+                                Some(exp) => Some(analyze_local_init(&elem_ty, exp, env)?),
+                                None => None,
+                            });
+                        }
+                        tree::Init::List(elems)
+                    },
+                },
+                None => tree::Init::None,
+            };
+            Ok(tree::VarDef { object, init })
+        }
+    }
+}
+
+/// Checks an initializer of a local object of type `ty`. A constant `char` is
+/// narrowed here rather than masked at run time.
+fn analyze_local_init<'ast>(ty: &Ty, exp: &'ast Exp, env: &mut SemaEnv<'ast>) -> Result<tree::Expr, SemaError> {
+    if *ty == Ty::Char {
+        if let Ok(val) = exp.evaluate(env) {
+            return Ok(tree::Expr::new(tree::ExprKind::Int(convert_const(ty, val)), Ty::Int));
+        }
+    }
+    let val = exp.analyze(env)?;
+    check_store_value(ty, val)
+}
+
+/// Resolves the object that `l_val` designates for storing into it.
+fn analyze_assign_place<'ast>(l_val: &'ast LVal, env: &mut SemaEnv<'ast>) -> Result<tree::Place, SemaError> {
+    if let LVal::Var(var) = l_val {
+        let is_const = match env.get_symbol(&var.ident) {
+            Some(SymbolInfo::Const(_)) => true,
+            Some(SymbolInfo::Object(object)) => env.get_object(*object).is_const,
+            _ => false,
+        };
+        if is_const {
+            return Err(SemaError::AssignToConst.at(l_val.span()));
+        }
+    }
+    let place = analyze_l_val_place(l_val, env).map_err(|err| err.at(l_val.span()))?;
+    match place.ty {
+        Ty::Array(..) => Err(SemaError::UsingArrayAsValue.at(l_val.span())),
+        Ty::Struct(_) => Err(SemaError::UsingStructAsValue.at(l_val.span())),
+        _ => Ok(place),
+    }
+}
+
+fn analyze_assign<'ast>(l_val: &'ast LVal, exp: &'ast Exp, env: &mut SemaEnv<'ast>) -> Result<tree::Stmt<'ast>, SemaError> {
+    let val = exp.analyze(env)?;
+    let place = analyze_assign_place(l_val, env)?;
+    let val = check_store_value(&place.ty, val).map_err(|err| err.at(exp.span))?;
+    Ok(tree::Stmt::Assign(place, val))
+}
+
+// A pointer moves by one element.
+fn analyze_inc_dec_place<'ast>(l_val: &'ast LVal, env: &mut SemaEnv<'ast>) -> Result<tree::Place, SemaError> {
+    let place = analyze_assign_place(l_val, env)?;
+    if let Ty::Pointer(_) = place.ty {
+        env.expect_extended("Pointer arithmetic")?;
+    }
+    Ok(place)
+}
+
+fn inc_dec_ty(ty: &Ty) -> Ty {
+    match ty {
+        Ty::Pointer(_) => ty.clone(),
+        _ => Ty::Int,
+    }
+}
+
+fn analyze_binary<'ast, L, R>(op: tree::BinaryOp, lhs: &'ast L, rhs: &'ast R, span: Span, env: &mut SemaEnv<'ast>) -> Result<tree::Expr, SemaError>
+where
+    L: Analyze<'ast, Out = tree::Expr>,
+    R: Analyze<'ast, Out = tree::Expr>,
+{
+    let (lhs, rhs) = (lhs.analyze(env)?, rhs.analyze(env)?);
+    // Koopa IR only compares integers, and a pointer can't be cast to one.
+    let is_pointer = |ty: &Ty| matches!(ty, Ty::Pointer(_));
+    if op.is_comparison() && (is_pointer(&lhs.ty) || is_pointer(&rhs.ty)) {
+        return Err(SemaError::PointerComparison.at(span));
+    }
+    let lhs = lhs.expect_int().map_err(|err| err.at(span))?;
+    let rhs = rhs.expect_int().map_err(|err| err.at(span))?;
+    Ok(tree::Expr::new(tree::ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)), Ty::Int))
+}
+
+/// Checks the definition of a label, which `goto`s anywhere in the function
+/// may jump to.
+fn analyze_label<'ast>(ident: &'ast str, span: Span, env: &mut SemaEnv<'ast>) -> Result<(), SemaError> {
+    let label = env.get_label_mut(ident);
+    if label.defined {
+        return Err(SemaError::DuplicateLabel.at(span));
+    }
+    label.defined = true;
+    Ok(())
+}
+
+fn analyze_if<'ast, T, E>(exp: &'ast Exp, then_stmt: &'ast T, else_stmt: Option<&'ast E>, env: &mut SemaEnv<'ast>) -> Result<tree::Stmt<'ast>, SemaError>
+where
+    T: Analyze<'ast, Out = tree::Stmt<'ast>>,
+    E: Analyze<'ast, Out = tree::Stmt<'ast>>,
+{
+    let cond = exp.analyze(env)?.expect_int().map_err(|err| err.at(exp.span))?;
+    let then_stmt = then_stmt.analyze(env)?;
+    let else_stmt = match else_stmt {
+        Some(else_stmt) => Some(Box::new(else_stmt.analyze(env)?)),
+        None => None,
+    };
+    Ok(tree::Stmt::If(cond, Box::new(then_stmt), else_stmt))
+}
+
+fn analyze_while<'ast, S>(exp: &'ast Exp, stmt: &'ast S, env: &mut SemaEnv<'ast>) -> Result<tree::Stmt<'ast>, SemaError>
+where
+    S: Analyze<'ast, Out = tree::Stmt<'ast>>,
+{
+    let cond = exp.analyze(env)?.expect_int().map_err(|err| err.at(exp.span))?;
+    env.enter_loop();
+    let body = stmt.analyze(env)?;
+    env.exit_loop();
+    Ok(tree::Stmt::While(cond, Box::new(body)))
+}
+
+fn analyze_for<'ast, S>(
+    for_init: &'ast Option<ForInit>,
+    exp: &'ast Option<Exp>,
+    for_step: &'ast Option<ForStep>,
+    stmt: &'ast S,
+    env: &mut SemaEnv<'ast>,
+) -> Result<tree::Stmt<'ast>, SemaError>
+where
+    S: Analyze<'ast, Out = tree::Stmt<'ast>>,
+{
+    // A declaration in the init clause is only visible inside the loop.
+    env.push_scope();
+    let init = match for_init {
+        Some(for_init) => for_init.analyze(env)?,
+        None => None,
+    };
+    let cond = match exp {
+        Some(exp) => Some(exp.analyze(env)?.expect_int().map_err(|err| err.at(exp.span))?),
+        None => None,
+    };
+    env.enter_loop();
+    let body = stmt.analyze(env)?;
+    env.exit_loop();
+    let step = match for_step {
+        Some(for_step) => Some(for_step.analyze(env)?),
+        None => None,
+    };
+    env.pop_scope();
+    Ok(tree::Stmt::For(init.map(Box::new), cond, step.map(Box::new), Box::new(body)))
+}
+
+fn analyze_switch<'ast>(exp: &'ast Exp, switch_item_list: &'ast [SwitchItem], env: &mut SemaEnv<'ast>) -> Result<tree::Stmt<'ast>, SemaError> {
+    // The labels come first, as in the generated code.
+    let mut case_vals = vec![];
+    let mut has_default = false;
+    for switch_item in switch_item_list {
+        match switch_item {
+            SwitchItem::Case(const_exp) => {
+                let val = const_exp.evaluate(env)?;
+                if case_vals.contains(&val) {
+                    return Err(SemaError::DuplicateCaseLabel.at(const_exp.span));
+                }
+                case_vals.push(val);
+            },
+            SwitchItem::Default(span) => {
+                if has_default {
+                    return Err(SemaError::DuplicateDefaultLabel.at(*span));
+                }
+                has_default = true;
+            },
+            SwitchItem::BlockItem(_) => {},
+        }
+    }
+
+    let cond = exp.analyze(env)?.expect_int().map_err(|err| err.at(exp.span))?;
+
+    env.enter_switch();
+    env.push_scope();
+    let mut case_vals = case_vals.into_iter();
+    let mut items = vec![];
+    for switch_item in switch_item_list {
+        match switch_item {
+            SwitchItem::Case(_) => items.push(tree::SwitchItem::Case(case_vals.next().unwrap())),
+            SwitchItem::Default(_) => items.push(tree::SwitchItem::Default),
+            SwitchItem::BlockItem(block_item) => items.extend(block_item.analyze(env)?.map(tree::SwitchItem::Stmt)),
+        }
+    }
+    env.pop_scope();
+    env.exit_switch();
+    Ok(tree::Stmt::Switch(cond, items))
+}
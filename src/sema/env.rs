@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+
+use super::symbol::{StructInfo, SymbolInfo, SymbolTable};
+use super::tree::{CompUnit, Func, FuncId, Item, Object, ObjectId};
+use super::ty::Ty;
+use super::SemaError;
+use crate::ast::BType;
+use crate::span::Span;
+
+/// A label of a `goto`, which is scoped to the whole function.
+pub struct Label {
+    pub defined: bool,
+    pub first_goto: Option<Span>, // Where the label is reported if it is never defined.
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum DeclType {
+    Global,
+    Local,
+    Static, // A static variable, which is stored like a global but visible only in its block, or in its file at file scope.
+}
+
+pub struct SemaEnv<'s> {
+    sym_tab: Vec<SymbolTable<'s>>,
+    objects: Vec<Object<'s>>,
+    funcs: Vec<Func<'s>>,
+    structs: Vec<StructInfo<'s>>,
+    labels: HashMap<&'s str, Label>,
+    cur_ret_ty: Option<Ty>,
+    loop_depth: usize, // The loops around the current statement, which `continue` needs.
+    breakable_depth: usize, // Likewise for the loops and switches, which `break` needs.
+    cur_decl_type: Option<DeclType>,
+    warnings: Vec<SemaError>,
+    extended: bool, // Whether pointers are allowed.
+}
+
+impl<'s> SemaEnv<'s> {
+    pub fn new(extended: bool) -> Self {
+        Self {
+            sym_tab: Vec::new(),
+            objects: Vec::new(),
+            funcs: Vec::new(),
+            structs: Vec::new(),
+            labels: HashMap::new(),
+            cur_ret_ty: None,
+            loop_depth: 0,
+            breakable_depth: 0,
+            cur_decl_type: None,
+            warnings: Vec::new(),
+            extended,
+        }
+    }
+
+    /// Finishes the analysis, returning the checked compile unit.
+    pub fn into_comp_unit(self, items: Vec<Item>) -> CompUnit<'s> {
+        CompUnit {
+            objects: self.objects,
+            funcs: self.funcs,
+            structs: self.structs,
+            items,
+            warnings: self.warnings,
+        }
+    }
+
+    /// Checks that `what`, which is only a part of extended SysY, is allowed.
+    pub fn expect_extended(&self, what: &'static str) -> Result<(), SemaError> {
+        if self.extended {
+            Ok(())
+        } else {
+            Err(SemaError::ExtendedOnly(what))
+        }
+    }
+
+    /// Reports a problem that doesn't stop the compilation.
+    pub fn warn(&mut self, warning: SemaError) {
+        self.warnings.push(warning);
+    }
+
+    pub fn push_scope(&mut self) {
+        self.sym_tab.push(SymbolTable::new());
+    }
+
+    pub fn pop_scope(&mut self) {
+        self.sym_tab.pop();
+    }
+
+    pub fn new_symbol_const(&mut self, ident: &'s str, val: i32) {
+        let cur_sym_tab = self.sym_tab.last_mut().unwrap();
+        cur_sym_tab.set_value(ident, SymbolInfo::Const(val));
+    }
+
+    pub fn new_symbol_type_alias(&mut self, ident: &'s str, b_type: &'s BType) {
+        let cur_sym_tab = self.sym_tab.last_mut().unwrap();
+        cur_sym_tab.set_value(ident, SymbolInfo::TypeAlias(b_type));
+    }
+
+    pub fn contains_symbol_in_cur_scope(&self, ident: &'s str) -> bool {
+        let cur_sym_tab = self.sym_tab.last().unwrap();
+        cur_sym_tab.contains_key(ident)
+    }
+
+    pub fn get_symbol(&self, ident: &'s str) -> Option<&SymbolInfo<'s>> {
+        for sym_tab in self.sym_tab.iter().rev() {
+            if let Some(symbol_info) = sym_tab.get_value(ident) {
+                return Some(symbol_info);
+            }
+        }
+        None
+    }
+
+    pub fn contains_symbol_in_global_scope(&self, ident: &'s str) -> bool {
+        let global_sym_tab = self.sym_tab.first().unwrap();
+        global_sym_tab.contains_key(ident)
+    }
+
+    pub fn new_symbol_const_in_global_scope(&mut self, ident: &'s str, val: i32) {
+        let global_sym_tab = self.sym_tab.first_mut().unwrap();
+        global_sym_tab.set_value(ident, SymbolInfo::Const(val));
+    }
+
+    /// Declares an object in the current scope.
+    pub fn new_object(&mut self, object: Object<'s>) -> ObjectId {
+        let id = ObjectId(self.objects.len());
+        let cur_sym_tab = self.sym_tab.last_mut().unwrap();
+        cur_sym_tab.set_value(object.ident, SymbolInfo::Object(id));
+        self.objects.push(object);
+        id
+    }
+
+    pub fn get_object(&self, id: ObjectId) -> &Object<'s> {
+        &self.objects[id.0]
+    }
+
+    pub fn get_object_mut(&mut self, id: ObjectId) -> &mut Object<'s> {
+        &mut self.objects[id.0]
+    }
+
+    /// Declares a function, which is always in the global scope.
+    pub fn new_func(&mut self, func: Func<'s>) -> FuncId {
+        let id = FuncId(self.funcs.len());
+        let global_sym_tab = self.sym_tab.first_mut().unwrap();
+        global_sym_tab.set_value(func.ident, SymbolInfo::Function(id));
+        self.funcs.push(func);
+        id
+    }
+
+    pub fn get_func(&self, ident: &'s str) -> Option<FuncId> {
+        let global_sym_tab = self.sym_tab.first().unwrap();
+        if let Some(SymbolInfo::Function(func)) = global_sym_tab.get_value(ident) {
+            Some(*func)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_func_info(&self, id: FuncId) -> &Func<'s> {
+        &self.funcs[id.0]
+    }
+
+    pub fn get_func_info_mut(&mut self, id: FuncId) -> &mut Func<'s> {
+        &mut self.funcs[id.0]
+    }
+
+    pub fn new_struct(&mut self, tag: &'s str, struct_info: StructInfo<'s>) {
+        self.structs.push(struct_info);
+        let cur_sym_tab = self.sym_tab.last_mut().unwrap();
+        cur_sym_tab.set_struct_tag(tag, self.structs.len() - 1);
+    }
+
+    pub fn contains_struct_in_cur_scope(&self, tag: &'s str) -> bool {
+        let cur_sym_tab = self.sym_tab.last().unwrap();
+        cur_sym_tab.get_struct_tag(tag).is_some()
+    }
+
+    pub fn get_struct_id(&self, tag: &'s str) -> Option<usize> {
+        self.sym_tab.iter().rev().find_map(|sym_tab| sym_tab.get_struct_tag(tag))
+    }
+
+    pub fn get_struct(&self, struct_id: usize) -> &StructInfo<'s> {
+        &self.structs[struct_id]
+    }
+
+    /// Returns the label, which is created on its first use.
+    pub fn get_label_mut(&mut self, ident: &'s str) -> &mut Label {
+        self.labels.entry(ident).or_insert(Label { defined: false, first_goto: None })
+    }
+
+    /// Forgets the labels of the current function and returns them.
+    pub fn take_labels(&mut self) -> HashMap<&'s str, Label> {
+        std::mem::take(&mut self.labels)
+    }
+
+    pub fn set_cur_ret_ty(&mut self, ty: Option<Ty>) {
+        self.cur_ret_ty = ty;
+    }
+
+    pub fn get_cur_ret_ty(&self) -> Option<&Ty> {
+        self.cur_ret_ty.as_ref()
+    }
+
+    pub fn enter_loop(&mut self) {
+        self.loop_depth += 1;
+        self.breakable_depth += 1;
+    }
+
+    pub fn exit_loop(&mut self) {
+        self.loop_depth -= 1;
+        self.breakable_depth -= 1;
+    }
+
+    pub fn enter_switch(&mut self) {
+        self.breakable_depth += 1;
+    }
+
+    pub fn exit_switch(&mut self) {
+        self.breakable_depth -= 1;
+    }
+
+    pub fn in_loop(&self) -> bool {
+        self.loop_depth > 0
+    }
+
+    pub fn in_breakable(&self) -> bool {
+        self.breakable_depth > 0
+    }
+
+    pub fn set_cur_decl_type(&mut self, decl_type: Option<DeclType>) {
+        self.cur_decl_type = decl_type;
+    }
+
+    pub fn get_cur_decl_type(&self) -> Option<DeclType> {
+        self.cur_decl_type
+    }
+}
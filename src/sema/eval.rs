@@ -0,0 +1,332 @@
+use crate::ast::*;
+
+use super::{env::SemaEnv, symbol::SymbolInfo, ty::Ty, SemaError};
+
+
+pub trait Evaluate {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError>;
+}
+
+impl Evaluate for LVal {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        match self {
+            Self::Var(var) => var.evaluate(env),
+            Self::Deref(_, _) => Err(SemaError::InitializeConstWithVariable),
+        }
+    }
+}
+
+impl Evaluate for VarRef {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        if let Some(symbol_info) = env.get_symbol(&self.ident) {
+            match symbol_info {
+                SymbolInfo::Const(val) => {
+                    if !self.index_list.is_empty() {
+                        return Err(SemaError::SubscriptOnNonArray);
+                    }
+                    if !self.field_list.is_empty() {
+                        return Err(SemaError::FieldAccessOnNonStruct);
+                    }
+                    Ok(*val)
+                },
+                SymbolInfo::Object(_) => Err(SemaError::InitializeConstWithVariable),
+                SymbolInfo::Function(_) => Err(SemaError::InitializeConstWithFunctionCall),
+                SymbolInfo::TypeAlias(_) => Err(SemaError::UseTypeAsVariable),
+            }
+        } else {
+            Err(SemaError::SymbolUndeclared)
+        }
+    }
+}
+
+impl Evaluate for ConstInitVal {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        match self {
+            Self::ConstExp(const_exp) => const_exp.evaluate(env),
+            Self::List(_) => Err(SemaError::InvalidInitializer),
+        }
+    }
+}
+
+impl Evaluate for ConstExp {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        self.exp.evaluate(env).map_err(|err| err.at(self.span))
+    }
+}
+
+impl Evaluate for InitVal {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        match self {
+            Self::Exp(exp) => exp.evaluate(env),
+            Self::List(_) => Err(SemaError::InvalidInitializer),
+        }
+    }
+}
+
+impl Evaluate for Exp {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        self.assign_exp.evaluate(env).map_err(|err| err.at(self.span))
+    }
+}
+
+impl Evaluate for AssignExp {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        match self {
+            Self::CondExp(cond_exp) => cond_exp.evaluate(env),
+            Self::CompoundAssign(l_val, _, _, _) => evaluate_modify(l_val, env),
+        }
+    }
+}
+
+impl Evaluate for CondExp {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        match self {
+            Self::LOrExp(l_or_exp) => l_or_exp.evaluate(env),
+            Self::Cond(l_or_exp, exp, cond_exp, _) => {
+                if l_or_exp.evaluate(env)? != 0 {
+                    exp.evaluate(env)
+                } else {
+                    cond_exp.evaluate(env)
+                }
+            }
+        }
+    }
+}
+
+impl Evaluate for LOrExp {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        match self {
+            Self::LAndExp(l_and_exp) => l_and_exp.evaluate(env),
+            // The right operand is only evaluated as it would be at run
+            // time, so `0 || 1 / 0` is an error but `1 || 1 / 0` isn't.
+            Self::Or(l_or_exp, l_and_exp, _) => {
+                if l_or_exp.evaluate(env)? != 0 {
+                    return Ok(1);
+                }
+                Ok((l_and_exp.evaluate(env)? != 0) as i32)
+            }
+        }       
+    }
+}
+
+impl Evaluate for LAndExp {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        match self {
+            Self::BitOrExp(bit_or_exp) => bit_or_exp.evaluate(env),
+            Self::And(l_and_exp, bit_or_exp, _) => {
+                if l_and_exp.evaluate(env)? == 0 {
+                    return Ok(0);
+                }
+                Ok((bit_or_exp.evaluate(env)? != 0) as i32)
+            }
+        }
+    }
+}
+
+impl Evaluate for BitOrExp {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        match self {
+            Self::BitXorExp(bit_xor_exp) => bit_xor_exp.evaluate(env),
+            Self::BitOr(bit_or_exp, bit_xor_exp, _) => {
+                let lhs = bit_or_exp.evaluate(env)?;
+                let rhs = bit_xor_exp.evaluate(env)?;
+                Ok(lhs | rhs)
+            }
+        }
+    }
+}
+
+impl Evaluate for BitXorExp {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        match self {
+            Self::BitAndExp(bit_and_exp) => bit_and_exp.evaluate(env),
+            Self::BitXor(bit_xor_exp, bit_and_exp, _) => {
+                let lhs = bit_xor_exp.evaluate(env)?;
+                let rhs = bit_and_exp.evaluate(env)?;
+                Ok(lhs ^ rhs)
+            }
+        }
+    }
+}
+
+impl Evaluate for BitAndExp {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        match self {
+            Self::EqExp(eq_exp) => eq_exp.evaluate(env),
+            Self::BitAnd(bit_and_exp, eq_exp, _) => {
+                let lhs = bit_and_exp.evaluate(env)?;
+                let rhs = eq_exp.evaluate(env)?;
+                Ok(lhs & rhs)
+            }
+        }
+    }
+}
+
+impl Evaluate for EqExp {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        match self {
+            Self::RelExp(rel_exp) => rel_exp.evaluate(env),
+            Self::Eq(eq_exp, rel_exp, _) => {
+                let lhs = eq_exp.evaluate(env)?;
+                let rhs = rel_exp.evaluate(env)?;
+                Ok((lhs == rhs) as i32)
+            }
+            Self::Ne(eq_exp, rel_exp, _) => {
+                let lhs = eq_exp.evaluate(env)?;
+                let rhs = rel_exp.evaluate(env)?;
+                Ok((lhs != rhs) as i32)
+            }
+        }
+    }
+}
+
+impl Evaluate for RelExp {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        match self {
+            Self::ShiftExp(shift_exp) => shift_exp.evaluate(env),
+            Self::Lt(rel_exp, shift_exp, _) => {
+                let lhs = rel_exp.evaluate(env)?;
+                let rhs = shift_exp.evaluate(env)?;
+                Ok((lhs < rhs) as i32)
+            }
+            Self::Gt(rel_exp, shift_exp, _) => {
+                let lhs = rel_exp.evaluate(env)?;
+                let rhs = shift_exp.evaluate(env)?;
+                Ok((lhs > rhs) as i32)
+            }
+            Self::Le(rel_exp, shift_exp, _) => {
+                let lhs = rel_exp.evaluate(env)?;
+                let rhs = shift_exp.evaluate(env)?;
+                Ok((lhs <= rhs) as i32)
+            }
+            Self::Ge(rel_exp, shift_exp, _) => {
+                let lhs = rel_exp.evaluate(env)?;
+                let rhs = shift_exp.evaluate(env)?;
+                Ok((lhs >= rhs) as i32)
+            }
+        }
+    }
+    
+}
+
+impl Evaluate for ShiftExp {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        match self {
+            Self::AddExp(add_exp) => add_exp.evaluate(env),
+            Self::Shl(shift_exp, add_exp, _) => {
+                let lhs = shift_exp.evaluate(env)?;
+                let rhs = add_exp.evaluate(env)?;
+                Ok(lhs.wrapping_shl(rhs as u32))
+            }
+            Self::Sar(shift_exp, add_exp, _) => {
+                let lhs = shift_exp.evaluate(env)?;
+                let rhs = add_exp.evaluate(env)?;
+                Ok(lhs.wrapping_shr(rhs as u32))
+            }
+        }
+    }
+}
+
+impl Evaluate for AddExp {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        match self {
+            Self::MulExp(mul_exp) => mul_exp.evaluate(env),
+            Self::Add(add_exp, mul_exp, _) => {
+                let lhs = add_exp.evaluate(env)?;
+                let rhs = mul_exp.evaluate(env)?;
+                Ok(lhs.wrapping_add(rhs))
+            }
+            Self::Sub(add_exp, mul_exp, _) => {
+                let lhs = add_exp.evaluate(env)?;
+                let rhs = mul_exp.evaluate(env)?;
+                Ok(lhs.wrapping_sub(rhs))
+            }
+        }
+    }
+}
+
+impl Evaluate for MulExp {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        match self {
+            Self::UnaryExp(unary_exp) => unary_exp.evaluate(env),
+            Self::Mul(mul_exp, unary_exp, _) => {
+                let lhs = mul_exp.evaluate(env)?;
+                let rhs = unary_exp.evaluate(env)?;
+                Ok(lhs.wrapping_mul(rhs))
+            }
+            Self::Div(mul_exp, unary_exp, span) => {
+                let lhs = mul_exp.evaluate(env)?;
+                let rhs = unary_exp.evaluate(env)?;
+                if rhs == 0 {
+                    return Err(SemaError::DivisionByZero.at(*span));
+                }
+                Ok(lhs.wrapping_div(rhs))
+            }
+            Self::Mod(mul_exp, unary_exp, span) => {
+                let lhs = mul_exp.evaluate(env)?;
+                let rhs = unary_exp.evaluate(env)?;
+                if rhs == 0 {
+                    return Err(SemaError::DivisionByZero.at(*span));
+                }
+                Ok(lhs.wrapping_rem(rhs))
+            }
+        }
+    }
+}
+
+impl Evaluate for UnaryExp {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        match self {
+            Self::PrimaryExp(primary_exp) => primary_exp.evaluate(env),
+            Self::FuncCall(_, _, span) => Err(SemaError::UseFunctionAsVariable.at(*span)),
+            Self::PrefixIncDec(_, l_val, _) | Self::PostfixIncDec(l_val, _, _) => evaluate_modify(l_val, env),
+            // An address is only known once the program is linked.
+            Self::AddrOf(l_val, _) => Err(SemaError::InitializeConstWithVariable.at(l_val.span())),
+            Self::UnaryExp(op, unary_exp, _) => {
+                let val = unary_exp.evaluate(env)?;
+                match op {
+                    UnaryOp::Plus => Ok(val),
+                    UnaryOp::Minus => Ok(val.wrapping_neg()),
+                    UnaryOp::Not => Ok((val == 0) as i32),
+                    UnaryOp::BitNot => Ok(!val),
+                }
+            }
+        }
+    }
+}
+
+impl Evaluate for PrimaryExp {
+    fn evaluate(&self, env: &SemaEnv) -> Result<i32, SemaError> {
+        match self {
+            Self::Exp(exp) => exp.evaluate(env),
+            Self::LVal(lval) => lval.evaluate(env).map_err(|err| err.at(lval.span())),
+            Self::Number(num) => Ok(*num),
+            Self::StringLiteral(_) => Err(SemaError::UsingArrayAsValue),
+        }
+    }
+}
+
+// Modifying an lvalue is never a constant expression.
+fn evaluate_modify(l_val: &LVal, env: &SemaEnv) -> Result<i32, SemaError> {
+    let err = match l_val {
+        LVal::Var(var) => match env.get_symbol(&var.ident) {
+            Some(SymbolInfo::Const(_)) => SemaError::AssignToConst,
+            Some(SymbolInfo::Object(object)) if env.get_object(*object).is_const => SemaError::AssignToConst,
+            Some(SymbolInfo::Function(_)) => SemaError::UseFunctionAsVariable,
+            Some(SymbolInfo::TypeAlias(_)) => SemaError::UseTypeAsVariable,
+            Some(_) => SemaError::InitializeConstWithVariable,
+            None => SemaError::SymbolUndeclared,
+        },
+        LVal::Deref(_, _) => SemaError::InitializeConstWithVariable,
+    };
+    Err(err.at(l_val.span()))
+}
+
+/// Converts a constant to `ty` for storing it. A `char` is an unsigned byte,
+/// as in the RISC-V ABI.
+pub fn convert_const(ty: &Ty, val: i32) -> i32 {
+    match ty {
+        Ty::Char => val as u8 as i32,
+        _ => val,
+    }
+}
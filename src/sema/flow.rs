@@ -0,0 +1,113 @@
+use super::label::ContainsLabel;
+use super::tree::*;
+
+/// Tells whether the end of the function body may be reached, so that the
+/// function would return without a value. The check is conservative: a
+/// condition that isn't a constant may go either way, and a label may be
+/// jumped to from anywhere.
+pub fn falls_through(block: &Block) -> bool {
+    let mut flow = Flow { breaks: vec![], continues: vec![] };
+    flow.reach_block(block, true)
+}
+
+struct Flow {
+    breaks: Vec<bool>, // Whether a `break` out of each enclosing loop or switch may be reached.
+    continues: Vec<bool>, // Likewise for a `continue` of each enclosing loop.
+}
+
+impl Flow {
+    fn reach_block(&mut self, block: &Block, live: bool) -> bool {
+        block.stmt_list.iter().fold(live, |live, stmt| self.reach(stmt, live))
+    }
+
+    /// Returns whether the end of `stmt` may be reached, given whether its
+    /// start may be.
+    fn reach(&mut self, stmt: &Stmt, live: bool) -> bool {
+        match stmt {
+            Stmt::Decl(_) | Stmt::Assign(..) | Stmt::Exp(_) => live,
+            Stmt::Block(block) => self.reach_block(block, live),
+            Stmt::If(_, then_stmt, else_stmt) => {
+                let then_live = self.reach(then_stmt, live);
+                let else_live = match else_stmt {
+                    Some(else_stmt) => self.reach(else_stmt, live),
+                    None => live,
+                };
+                then_live || else_live
+            },
+            Stmt::While(cond, body) => {
+                let (body_live, broke, _) = self.reach_loop(body, live);
+                if is_true(cond) {
+                    broke
+                } else {
+                    live || body_live || broke || body.contains_label()
+                }
+            },
+            Stmt::For(init, cond, _, body) => {
+                let live = match init {
+                    Some(init) => self.reach(init, live),
+                    None => live,
+                };
+                let (body_live, broke, _) = self.reach_loop(body, live);
+                match cond {
+                    Some(cond) if !is_true(cond) => live || body_live || broke || body.contains_label(),
+                    _ => broke,
+                }
+            },
+            Stmt::DoWhile(body, cond) => {
+                let (body_live, broke, continued) = self.reach_loop(body, live);
+                if is_true(cond) {
+                    broke
+                } else {
+                    body_live || continued || broke
+                }
+            },
+            Stmt::Switch(_, switch_item_list) => {
+                self.breaks.push(false);
+                let mut item_live = false;
+                let mut has_default = false;
+                for switch_item in switch_item_list {
+                    match switch_item {
+                        SwitchItem::Case(_) => item_live |= live,
+                        SwitchItem::Default => {
+                            item_live |= live;
+                            has_default = true;
+                        },
+                        SwitchItem::Stmt(stmt) => item_live = self.reach(stmt, item_live),
+                    }
+                }
+                let broke = self.breaks.pop().unwrap();
+                item_live || broke || (live && !has_default)
+            },
+            Stmt::Break => {
+                if let Some(broke) = self.breaks.last_mut() {
+                    *broke |= live;
+                }
+                false
+            },
+            Stmt::Continue => {
+                if let Some(continued) = self.continues.last_mut() {
+                    *continued |= live;
+                }
+                false
+            },
+            Stmt::Goto(_) | Stmt::Return(_) => false,
+            Stmt::Labeled(_, stmt) => self.reach(stmt, true),
+        }
+    }
+
+    /// Walks the body of a loop. Returns whether its end, a `break` out of it
+    /// and a `continue` of it may be reached.
+    fn reach_loop(&mut self, body: &Stmt, live: bool) -> (bool, bool, bool) {
+        self.breaks.push(false);
+        self.continues.push(false);
+        let body_live = self.reach(body, live);
+        let continued = self.continues.pop().unwrap();
+        let broke = self.breaks.pop().unwrap();
+        (body_live, broke, continued)
+    }
+}
+
+/// Whether the condition is a constant that holds.
+fn is_true(cond: &Expr) -> bool {
+    matches!(cond.kind, ExprKind::Int(val) if val != 0)
+}
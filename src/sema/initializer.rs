@@ -0,0 +1,98 @@
+use crate::ast::*;
+
+use super::SemaError;
+
+/// An initializer that may be flattened according to the shape of an array.
+pub trait Flatten {
+    /// Flattens the initializer into a list of `product(dims)` elements in
+    /// row-major order. Elements that are not explicitly initialized are `None`
+    /// and should be filled with zero.
+    fn flatten(&self, dims: &[usize]) -> Result<Vec<Option<&Exp>>, SemaError>;
+
+    /// Returns the bytes of the string literal that the initializer is, which
+    /// initializes a `char` array instead of a list.
+    fn as_string_literal(&self) -> Option<&[u8]>;
+}
+
+trait InitItem: Sized {
+    fn as_exp(&self) -> Option<&Exp>;
+    fn as_list(&self) -> Option<&[Self]>;
+}
+
+impl InitItem for InitVal {
+    fn as_exp(&self) -> Option<&Exp> {
+        match self {
+            Self::Exp(exp) => Some(exp),
+            Self::List(_) => None,
+        }
+    }
+
+    fn as_list(&self) -> Option<&[Self]> {
+        match self {
+            Self::Exp(_) => None,
+            Self::List(list) => Some(list),
+        }
+    }
+}
+
+impl InitItem for ConstInitVal {
+    fn as_exp(&self) -> Option<&Exp> {
+        match self {
+            Self::ConstExp(const_exp) => Some(&const_exp.exp),
+            Self::List(_) => None,
+        }
+    }
+
+    fn as_list(&self) -> Option<&[Self]> {
+        match self {
+            Self::ConstExp(_) => None,
+            Self::List(list) => Some(list),
+        }
+    }
+}
+
+impl<T: InitItem> Flatten for T {
+    fn flatten(&self, dims: &[usize]) -> Result<Vec<Option<&Exp>>, SemaError> {
+        match self.as_list() {
+            Some(list) => {
+                let mut elems = Vec::new();
+                flatten_list(list, dims, &mut elems)?;
+                Ok(elems)
+            }
+            None => Err(SemaError::InvalidInitializer),
+        }
+    }
+
+    fn as_string_literal(&self) -> Option<&[u8]> {
+        self.as_exp().and_then(Exp::as_string_literal)
+    }
+}
+
+/// Appends the elements of `list`, which initializes an aggregate of shape
+/// `dims`, to `elems`.
+///
+/// A scalar item fills the next element. A braced item initializes the largest
+/// sub-aggregate that starts at the current position, so the position must be
+/// aligned to at least the last dimension.
+fn flatten_list<'a, T: InitItem>(list: &'a [T], dims: &[usize], elems: &mut Vec<Option<&'a Exp>>) -> Result<(), SemaError> {
+    let total: usize = dims.iter().product();
+    let start = elems.len();
+    for item in list {
+        let pos = elems.len() - start;
+        if pos >= total {
+            return Err(SemaError::InvalidInitializer);
+        }
+        match item.as_exp() {
+            Some(exp) => elems.push(Some(exp)),
+            None => {
+                let sub_dims = (1..dims.len())
+                    .map(|k| &dims[k..])
+                    .find(|sub_dims| pos.is_multiple_of(sub_dims.iter().product::<usize>()))
+                    .ok_or(SemaError::InvalidInitializer)?;
+                flatten_list(item.as_list().unwrap(), sub_dims, elems)?;
+            }
+        }
+    }
+    elems.resize(start + total, None);
+    Ok(())
+}
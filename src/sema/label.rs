@@ -0,0 +1,25 @@
+use super::tree::*;
+
+/// A statement that may contain a label for `goto`.
+pub trait ContainsLabel {
+    fn contains_label(&self) -> bool;
+}
+
+impl ContainsLabel for Stmt<'_> {
+    fn contains_label(&self) -> bool {
+        match self {
+            Self::Block(block) => block.stmt_list.iter().any(|stmt| stmt.contains_label()),
+            Self::If(_, then_stmt, else_stmt) => {
+                then_stmt.contains_label() || else_stmt.as_ref().is_some_and(|stmt| stmt.contains_label())
+            },
+            Self::While(_, stmt) | Self::DoWhile(stmt, _) => stmt.contains_label(),
+            Self::For(_, _, _, stmt) => stmt.contains_label(),
+            Self::Switch(_, switch_item_list) => switch_item_list.iter().any(|item| match item {
+                SwitchItem::Stmt(stmt) => stmt.contains_label(),
+                SwitchItem::Case(_) | SwitchItem::Default => false,
+            }),
+            Self::Labeled(..) => true,
+            Self::Decl(_) | Self::Assign(..) | Self::Exp(_) | Self::Break | Self::Continue | Self::Goto(_) | Self::Return(_) => false,
+        }
+    }
+}
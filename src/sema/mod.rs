@@ -0,0 +1,138 @@
+mod check;
+mod env;
+mod eval;
+mod flow;
+mod initializer;
+pub mod label;
+mod symbol;
+pub mod tree;
+pub mod ty;
+
+use crate::ast::CompUnit;
+use crate::span::Span;
+use check::Analyze;
+use env::SemaEnv;
+use std::fmt;
+use std::result::Result;
+
+/// Checks the given compile unit (ASTs), resolving every name to what it
+/// declares and typing every expression. Constant expressions are folded.
+/// Pointers are only allowed in extended SysY.
+pub fn analyze(comp_unit: &CompUnit, extended: bool) -> Result<tree::CompUnit<'_>, SemaError> {
+    let mut env = SemaEnv::new(extended);
+    let items = comp_unit.analyze(&mut env)?;
+    Ok(env.into_comp_unit(items))
+}
+
+pub enum SemaError {
+    UnknownType,
+    SymbolDeclaredMoreThanOnce,
+    SymbolUndeclared,
+    AssignToConst,
+    InitializeConstWithVariable,
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    UseFunctionAsVariable,
+    FunctionUndeclared,
+    InitializeConstWithFunctionCall,
+    ReturnWithExpressionInVoidFunction,
+    UsingVoidValue,
+    InvalidArraySize,
+    InvalidInitializer,
+    SubscriptOnNonArray,
+    UsingArrayAsValue,
+    DivisionByZero,
+    DuplicateCaseLabel,
+    DuplicateDefaultLabel,
+    ConflictingFunctionDeclaration,
+    TooManyArguments(String, usize, usize), // The function, and the expected and actual numbers of arguments.
+    TooFewArguments(String, usize, usize),
+    DuplicateLabel,
+    UndefinedLabel,
+    UseTypeAsVariable,
+    UsingStructAsValue,
+    UnknownField,
+    FieldAccessOnNonStruct,
+    TypeMismatch,
+    AddressOfConst,
+    DerefNonPointer,
+    MissingReturn(String),
+    NonLiteralRuntimeString,
+    ExtendedOnly(&'static str), // What is used.
+    PointerComparison,
+    PointerDifference,
+    NonConstStaticInitializer,
+    Located(Box<SemaError>, Span), // The error with the span of the innermost AST node that caused it.
+}
+
+impl SemaError {
+    /// Attaches `span` to the error unless it is already located.
+    pub fn at(self, span: Span) -> Self {
+        match self {
+            Self::Located(_, _) => self,
+            _ => Self::Located(Box::new(self), span),
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::Located(_, span) => Some(*span),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownType => write!(f, "Unknown variable type"),
+            Self::SymbolDeclaredMoreThanOnce => write!(f, "Symbol declared more than once"),
+            Self::SymbolUndeclared => write!(f, "Symbol undeclared"),
+            Self::AssignToConst => write!(f, "Assigning to a const symbol"),
+            Self::InitializeConstWithVariable => write!(f, "Initializing a const symbol with a variable"),
+            Self::BreakOutsideLoop => write!(f, "Break statement outside loop or switch"),
+            Self::ContinueOutsideLoop => write!(f, "Continue statement outside loop"),
+            Self::UseFunctionAsVariable => write!(f, "Using a function as a variable"),
+            Self::FunctionUndeclared => write!(f, "Function undeclared"),
+            Self::InitializeConstWithFunctionCall => write!(f, "Initializing a const symbol with a function call"),
+            Self::ReturnWithExpressionInVoidFunction => write!(f, "Returning with an expression in a void function"),
+            Self::UsingVoidValue => write!(f, "Using a void value"),
+            Self::InvalidArraySize => write!(f, "Array size must be a positive constant"),
+            Self::InvalidInitializer => write!(f, "Invalid initializer list"),
+            Self::SubscriptOnNonArray => write!(f, "Subscripting a non-array symbol"),
+            Self::UsingArrayAsValue => write!(f, "Using an array as a value"),
+            Self::DivisionByZero => write!(f, "Division by zero in a constant expression"),
+            Self::DuplicateCaseLabel => write!(f, "Duplicate case label"),
+            Self::DuplicateDefaultLabel => write!(f, "Multiple default labels in one switch"),
+            Self::ConflictingFunctionDeclaration => write!(f, "Conflicting declarations of a function"),
+            Self::TooManyArguments(ident, expected, actual) => {
+                write!(f, "Too many arguments in a call to `{}`: expected {}, found {}", ident, expected, actual)
+            },
+            Self::TooFewArguments(ident, expected, actual) => {
+                write!(f, "Too few arguments in a call to `{}`: expected {}, found {}", ident, expected, actual)
+            },
+            Self::DuplicateLabel => write!(f, "Duplicate label"),
+            Self::UndefinedLabel => write!(f, "Label used but not defined"),
+            Self::UseTypeAsVariable => write!(f, "Using a type name as a variable"),
+            Self::UsingStructAsValue => write!(f, "Using a struct as a value"),
+            Self::UnknownField => write!(f, "No such field in the struct"),
+            Self::FieldAccessOnNonStruct => write!(f, "Accessing a field of a non-struct value"),
+            Self::TypeMismatch => write!(f, "Mismatched types"),
+            Self::AddressOfConst => write!(f, "Taking the address of a const symbol"),
+            Self::DerefNonPointer => write!(f, "Dereferencing a non-pointer value"),
+            Self::MissingReturn(ident) => write!(f, "Not all paths return a value in `{}`", ident),
+            Self::NonLiteralRuntimeString => write!(f, "Only a string literal can be passed to the runtime library as a `char *`"),
+            Self::ExtendedOnly(what) => write!(f, "{} is only allowed in extended SysY, enabled by `-ext`", what),
+            Self::PointerComparison => write!(f, "Comparing pointers is not supported, as Koopa IR can't compare them"),
+            Self::PointerDifference => write!(f, "Subtracting pointers is not supported, as Koopa IR can't subtract them"),
+            Self::NonConstStaticInitializer => write!(f, "Initializer of a static variable must be a constant expression"),
+            Self::Located(err, _) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl fmt::Debug for SemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
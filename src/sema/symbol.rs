@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use super::tree::{FuncId, ObjectId};
+use super::ty::Ty;
+use crate::ast::BType;
+
+#[derive(Clone, Copy)]
+pub enum SymbolInfo<'s> {
+    Const(i32),
+    Object(ObjectId), // A variable, an array or a parameter, which the IR stores in memory.
+    Function(FuncId),
+    TypeAlias(&'s BType), // The aliased type, which is never an alias itself.
+}
+
+/// A struct type. Every field takes whole words, so that a struct is laid out
+/// as an `[i32, size]` array in the IR.
+#[derive(Debug)]
+pub struct StructInfo<'s> {
+    pub field_list: Vec<FieldInfo<'s>>,
+    pub size: usize, // In words.
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldInfo<'s> {
+    pub ident: &'s str,
+    pub ty: FieldType,
+    pub dims: Vec<usize>, // Empty unless the field is an array.
+    pub offset: usize, // In words.
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldType {
+    Int,
+    Char,
+    Struct(usize), // The id of the struct type in `CompUnit::structs`.
+}
+
+impl FieldType {
+    pub fn to_ty(self) -> Ty {
+        match self {
+            Self::Int => Ty::Int,
+            Self::Char => Ty::Char,
+            Self::Struct(struct_id) => Ty::Struct(struct_id),
+        }
+    }
+}
+
+impl<'s> StructInfo<'s> {
+    pub fn get_field(&self, ident: &str) -> Option<&FieldInfo<'s>> {
+        self.field_list.iter().find(|field| field.ident == ident)
+    }
+}
+
+pub struct SymbolTable<'s> {
+    table: HashMap<&'s str, SymbolInfo<'s>>,
+    struct_tags: HashMap<&'s str, usize>, // Struct tags live in a namespace of their own.
+}
+
+impl<'s> SymbolTable<'s> {
+    pub fn new() -> Self {
+        Self {
+            table: HashMap::new(),
+            struct_tags: HashMap::new(),
+        }
+    }
+
+    pub fn set_value(&mut self, ident: &'s str, info: SymbolInfo<'s>) {
+        self.table.insert(ident, info);
+    }
+
+    pub fn get_value(&self, ident: &'s str) -> Option<&SymbolInfo<'s>> {
+        self.table.get(ident)
+    }
+
+    pub fn contains_key(&self, ident: &'s str) -> bool {
+        self.table.contains_key(ident)
+    }
+
+    pub fn set_struct_tag(&mut self, tag: &'s str, struct_id: usize) {
+        self.struct_tags.insert(tag, struct_id);
+    }
+
+    pub fn get_struct_tag(&self, tag: &'s str) -> Option<usize> {
+        self.struct_tags.get(tag).copied()
+    }
+
+}
+
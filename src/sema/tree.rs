@@ -0,0 +1,229 @@
+//! The checked form of a compile unit, where every name is resolved to what it
+//! declares and every expression has a type. Constant expressions are folded
+//! into integers, and declarations that only name a constant or a type are
+//! gone.
+
+use super::symbol::StructInfo;
+use super::ty::Ty;
+use super::SemaError;
+use crate::ast::{AssignOp, IncDecOp};
+use crate::span::Span;
+
+#[derive(Debug)]
+pub struct CompUnit<'ast> {
+    pub objects: Vec<Object<'ast>>,
+    pub funcs: Vec<Func<'ast>>,
+    pub structs: Vec<StructInfo<'ast>>,
+    pub items: Vec<Item>, // The global declarations and the functions in source order.
+    pub warnings: Vec<SemaError>,
+}
+
+impl<'ast> CompUnit<'ast> {
+    pub fn object(&self, id: ObjectId) -> &Object<'ast> {
+        &self.objects[id.0]
+    }
+
+    pub fn func(&self, id: FuncId) -> &Func<'ast> {
+        &self.funcs[id.0]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjectId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FuncId(pub usize);
+
+/// A variable, an array or a parameter, which is stored in memory.
+#[derive(Debug)]
+pub struct Object<'ast> {
+    pub ident: &'ast str,
+    pub ty: Ty,
+    pub storage: Storage,
+    pub is_const: bool, // Only a const array is an object, as a scalar constant is folded.
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Storage {
+    Global,
+    Static, // Stored like a global but visible only in its block, or in its file at file scope.
+    Extern, // A global defined in another translation unit.
+    Local,
+    Param,
+}
+
+#[derive(Debug)]
+pub struct Func<'ast> {
+    pub ident: &'ast str,
+    pub ret_ty: Ty,
+    pub params_ty: Vec<Ty>,
+    pub is_variadic: bool, // Takes any number of arguments after its parameters.
+    pub is_library: bool, // Declared by the runtime library, and not defined in this unit.
+    pub is_called: bool, // Called somewhere in this unit.
+    pub body: Option<FuncBody<'ast>>,
+}
+
+#[derive(Debug)]
+pub struct FuncBody<'ast> {
+    pub params: Vec<Option<ObjectId>>, // `None` for an unnamed parameter.
+    pub block: Block<'ast>,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum Item {
+    Var(VarDef),
+    FuncDecl(FuncId),
+    FuncDef(FuncId),
+}
+
+#[derive(Debug)]
+pub struct VarDef {
+    pub object: ObjectId,
+    pub init: Init,
+}
+
+#[derive(Debug)]
+pub enum Init {
+    None,
+    Const(Vec<i32>), // The folded elements in row-major order, already converted to the element type.
+    Exp(Expr),
+    List(Vec<Option<Expr>>), // The elements in row-major order. `None` is a zero.
+}
+
+#[derive(Debug)]
+pub struct Block<'ast> {
+    pub stmt_list: Vec<Stmt<'ast>>,
+}
+
+#[derive(Debug)]
+pub enum Stmt<'ast> {
+    Decl(Vec<VarDef>),
+    Assign(Place, Expr),
+    Exp(Option<Expr>),
+    Block(Block<'ast>),
+    If(Expr, Box<Stmt<'ast>>, Option<Box<Stmt<'ast>>>),
+    While(Expr, Box<Stmt<'ast>>),
+    DoWhile(Box<Stmt<'ast>>, Expr),
+    For(Option<Box<Stmt<'ast>>>, Option<Expr>, Option<Box<Stmt<'ast>>>, Box<Stmt<'ast>>), // The init, the condition, the step and the body.
+    Switch(Expr, Vec<SwitchItem<'ast>>),
+    Break,
+    Continue,
+    Goto(&'ast str),
+    Labeled(&'ast str, Box<Stmt<'ast>>),
+    Return(Option<Expr>),
+}
+
+#[derive(Debug)]
+pub enum SwitchItem<'ast> {
+    Case(i32),
+    Default,
+    Stmt(Stmt<'ast>),
+}
+
+/// An object in memory, which may be read, written or have its address taken.
+#[derive(Debug)]
+pub struct Place {
+    pub kind: PlaceKind,
+    pub ty: Ty,
+}
+
+#[derive(Debug)]
+pub enum PlaceKind {
+    Object(ObjectId),
+    Index(Box<Place>, Box<Expr>), // An element of an array.
+    PtrIndex(Box<Place>, Box<Expr>), // An element that a pointer stored in the place points to.
+    Deref(Box<Expr>),
+    Field(Box<Place>, Vec<FieldStep>), // A field of a struct, reached by one or more field accesses.
+}
+
+/// A field access, which moves a pointer to the first word of a struct to the
+/// first word of the field.
+#[derive(Debug)]
+pub struct FieldStep {
+    pub offset: usize, // In words.
+    pub index_list: Vec<(Expr, usize)>, // The subscripts, each with its stride in words.
+}
+
+#[derive(Debug)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub ty: Ty, // Never an array or a struct, as those are only places.
+}
+
+#[derive(Debug)]
+pub enum ExprKind {
+    Int(i32),
+    Load(Box<Place>),
+    Decay(Box<Place>), // An array as a pointer to its first element.
+    AddrOf(Box<Place>),
+    String(Vec<u8>), // The bytes without the trailing NUL, one `char` per word.
+    PackedString(Vec<u8>), // The same, packed four bytes per word as the runtime library reads them.
+    Call(FuncId, Vec<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    PtrAdd(Box<Expr>, Box<Expr>), // A pointer plus an integer, in either order.
+    PtrSub(Box<Expr>, Box<Expr>), // A pointer minus an integer.
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Cond(Box<Expr>, Box<Expr>, Box<Expr>),
+    CompoundAssign(Box<Place>, AssignOp, Box<Expr>),
+    PrefixIncDec(IncDecOp, Box<Place>),
+    PostfixIncDec(Box<Place>, IncDecOp),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum UnaryOp {
+    Minus,
+    Not,
+    BitNot,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum BinaryOp {
+    Mul,
+    Div,
+    Mod,
+    Add,
+    Sub,
+    Shl,
+    Sar,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Eq,
+    Ne,
+    BitAnd,
+    BitXor,
+    BitOr,
+}
+
+impl BinaryOp {
+    pub fn is_comparison(self) -> bool {
+        matches!(self, Self::Lt | Self::Gt | Self::Le | Self::Ge | Self::Eq | Self::Ne)
+    }
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, ty: Ty) -> Self {
+        Self { kind, ty }
+    }
+
+    /// Checks that the expression is an integer.
+    pub fn expect_int(self) -> Result<Self, SemaError> {
+        match self.ty {
+            Ty::Int | Ty::Char => Ok(self),
+            Ty::Void => Err(SemaError::UsingVoidValue),
+            _ => Err(SemaError::TypeMismatch),
+        }
+    }
+
+    /// Checks that the expression has a value.
+    pub fn expect_value(self) -> Result<Self, SemaError> {
+        match self.ty {
+            Ty::Void => Err(SemaError::UsingVoidValue),
+            _ => Ok(self),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+/// The type of an object or a value, which tells apart what the IR can't,
+/// such as a `char` from an `int`, or a struct from an array of words.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+    Int,
+    Char,
+    Void,
+    Pointer(Box<Ty>),
+    Array(Box<Ty>, usize),
+    Struct(usize), // The id of the struct type in `CompUnit::structs`.
+}
+
+impl Ty {
+    /// Wraps the type in arrays of the given dimensions.
+    pub fn with_dims(self, dims: &[usize]) -> Ty {
+        dims.iter().rev().fold(self, |ty, &dim| Ty::Array(Box::new(ty), dim))
+    }
+
+    /// Splits an array type into its dimensions and its element type, which
+    /// is the type itself if it isn't an array.
+    pub fn dims(&self) -> (Vec<usize>, &Ty) {
+        let mut dims = vec![];
+        let mut ty = self;
+        while let Ty::Array(elem, len) = ty {
+            dims.push(*len);
+            ty = elem;
+        }
+        (dims, ty)
+    }
+
+    /// Whether the two types are the same in the IR, where a `char` is an
+    /// `int`.
+    pub fn same_repr(&self, other: &Ty) -> bool {
+        match (self, other) {
+            (Ty::Int | Ty::Char, Ty::Int | Ty::Char) => true,
+            (Ty::Pointer(a), Ty::Pointer(b)) => a.same_repr(b),
+            (Ty::Array(a, m), Ty::Array(b, n)) => m == n && a.same_repr(b),
+            _ => self == other,
+        }
+    }
+}
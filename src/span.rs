@@ -0,0 +1,65 @@
+/// A range of byte offsets `[start, end)` in the source file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+}
+
+/// Maps byte offsets in the source file to line and column numbers.
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> Self {
+        let mut line_starts = vec![0];
+        for (offset, c) in source.char_indices() {
+            if c == '\n' {
+                line_starts.push(offset + 1);
+            }
+        }
+        Self { line_starts }
+    }
+
+    /// Returns the 1-based line and column of the given byte offset, which
+    /// must be on a char boundary. The column counts characters, not bytes.
+    pub fn line_col(&self, source: &str, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let col = source[self.line_starts[line]..offset].chars().count();
+        (line + 1, col + 1)
+    }
+
+    /// Returns the byte range of the given 1-based line, excluding the line break.
+    pub fn line_range(&self, source: &str, line: usize) -> (usize, usize) {
+        let start = self.line_starts[line - 1];
+        let end = self.line_starts.get(line).copied().unwrap_or(source.len());
+        let text = &source[start..end];
+        (start, start + text.trim_end_matches(['\n', '\r']).len())
+    }
+}
+
+/// Moves `offset` back to the nearest char boundary of `source`, clamping it
+/// to the end of the source.
+pub fn floor_char_boundary(source: &str, offset: usize) -> usize {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+/// Moves `offset` forward to the nearest char boundary of `source`, clamping
+/// it to the end of the source.
+pub fn ceil_char_boundary(source: &str, offset: usize) -> usize {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset += 1;
+    }
+    offset
+}
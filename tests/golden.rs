@@ -0,0 +1,371 @@
+//! Golden tests, which run the compiler on the programs in `tests/golden` and
+//! compare what it writes with the expected files next to them. Setting
+//! `UPDATE_GOLDEN=1` rewrites the expected files instead.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const GOLDEN_DIR: &str = "tests/golden";
+
+/// The outcome of a run of the compiler.
+struct Run {
+    code: i32,
+    output: String, // What it wrote to the output file, if anything.
+    stderr: String,
+}
+
+/// Compiles `inputs` from the golden directory in `mode`, passing `options`
+/// after the mode.
+fn compile(mode: &str, options: &[&str], inputs: &[&str]) -> Run {
+    let output_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.out", inputs.join("+")));
+    let _ = fs::remove_file(&output_path);
+    let result = Command::new(env!("CARGO_BIN_EXE_sysy_compiler"))
+        .arg(mode)
+        .args(options)
+        .args(inputs.iter().map(|input| format!("{}/{}", GOLDEN_DIR, input)))
+        .arg("-o")
+        .arg(&output_path)
+        .output()
+        .expect("failed to run the compiler");
+    Run {
+        code: result.status.code().unwrap_or(-1),
+        output: fs::read_to_string(&output_path).unwrap_or_default(),
+        stderr: String::from_utf8(result.stderr).unwrap(),
+    }
+}
+
+/// Compares `actual` with the golden file `name`.
+fn assert_golden(name: &str, actual: &str) {
+    let path = Path::new(GOLDEN_DIR).join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|err| panic!("can't read {}: {}", path.display(), err));
+    assert!(expected == actual, "{} differs from the output:\n{}", path.display(), actual);
+}
+
+/// Checks the Koopa IR of a program that compiles.
+fn check_koopa(options: &[&str], inputs: &[&str], expected: &str) {
+    let run = compile("-koopa", options, inputs);
+    assert_eq!(run.code, 0, "the compiler failed:\n{}", run.stderr);
+    assert_golden(expected, &run.output);
+}
+
+/// Checks the RISC-V assembly of a program that compiles.
+fn check_riscv(options: &[&str], inputs: &[&str], expected: &str) {
+    let run = compile("-riscv", options, inputs);
+    assert_eq!(run.code, 0, "the compiler failed:\n{}", run.stderr);
+    assert_golden(expected, &run.output);
+}
+
+/// Checks the warnings of a program that compiles.
+fn check_warning(options: &[&str], inputs: &[&str], expected: &str) {
+    let run = compile("-koopa", options, inputs);
+    assert_eq!(run.code, 0, "the compiler failed:\n{}", run.stderr);
+    assert_golden(expected, &run.stderr);
+}
+
+/// Checks the diagnostics of a program that doesn't compile.
+fn check_error(options: &[&str], inputs: &[&str], expected: &str) {
+    let run = compile("-koopa", options, inputs);
+    assert_ne!(run.code, 0, "the compiler succeeded");
+    assert_golden(expected, &run.stderr);
+}
+
+#[test]
+fn multi_dim_array_init() {
+    check_koopa(&[], &["multi_dim_array_init.sy"], "multi_dim_array_init.koopa");
+}
+
+#[test]
+fn multi_dim_array_init_excess() {
+    check_error(&[], &["multi_dim_array_init_excess.sy"], "multi_dim_array_init_excess.err");
+}
+
+#[test]
+fn array_params() {
+    check_koopa(&[], &["array_params.sy"], "array_params.koopa");
+}
+
+#[test]
+fn array_param_mismatch() {
+    check_error(&[], &["array_param_mismatch.sy"], "array_param_mismatch.err");
+}
+
+#[test]
+fn span_binary_exp() {
+    check_error(&[], &["span_binary_exp.sy"], "span_binary_exp.err");
+}
+
+#[test]
+fn span_while_cond() {
+    check_error(&[], &["span_while_cond.sy"], "span_while_cond.err");
+}
+
+#[test]
+fn syntax_unexpected_token() {
+    check_error(&[], &["syntax_unexpected_token.sy"], "syntax_unexpected_token.err");
+}
+
+#[test]
+fn syntax_non_ascii() {
+    check_error(&[], &["syntax_non_ascii.sy"], "syntax_non_ascii.err");
+}
+
+#[test]
+fn syntax_eof() {
+    check_error(&[], &["syntax_eof.sy"], "syntax_eof.err");
+}
+
+#[test]
+fn syntax_recovery() {
+    check_error(&[], &["syntax_recovery.sy"], "syntax_recovery.err");
+}
+
+#[test]
+fn int_literals() {
+    check_koopa(&[], &["int_literals.sy"], "int_literals.koopa");
+}
+
+#[test]
+fn int_literal_overflow() {
+    check_error(&[], &["int_literal_overflow.sy"], "int_literal_overflow.err");
+}
+
+#[test]
+fn for_loop() {
+    check_koopa(&[], &["for_loop.sy"], "for_loop.koopa");
+}
+
+#[test]
+fn continue_outside_loop() {
+    check_error(&[], &["continue_outside_loop.sy"], "continue_outside_loop.err");
+}
+
+#[test]
+fn do_while() {
+    check_koopa(&[], &["do_while.sy"], "do_while.koopa");
+}
+
+#[test]
+fn compound_assign() {
+    check_koopa(&[], &["compound_assign.sy"], "compound_assign.koopa");
+}
+
+#[test]
+fn assign_to_const() {
+    check_error(&[], &["assign_to_const.sy"], "assign_to_const.err");
+}
+
+#[test]
+fn conditional() {
+    check_koopa(&[], &["conditional.sy"], "conditional.koopa");
+}
+
+#[test]
+fn const_division_by_zero() {
+    check_error(&[], &["const_division_by_zero.sy"], "const_division_by_zero.err");
+}
+
+#[test]
+fn bitwise() {
+    check_koopa(&[], &["bitwise.sy"], "bitwise.koopa");
+}
+
+#[test]
+fn switch_dense() {
+    check_koopa(&[], &["switch_dense.sy"], "switch_dense.koopa");
+    check_riscv(&[], &["switch_dense.sy"], "switch_dense.s");
+}
+
+#[test]
+fn switch_sparse() {
+    check_koopa(&[], &["switch_sparse.sy"], "switch_sparse.koopa");
+}
+
+#[test]
+fn switch_duplicate_case() {
+    check_error(&[], &["switch_duplicate_case.sy"], "switch_duplicate_case.err");
+}
+
+#[test]
+fn prototypes() {
+    check_koopa(&[], &["prototypes.sy"], "prototypes.koopa");
+}
+
+#[test]
+fn conflicting_prototype() {
+    check_error(&[], &["conflicting_prototype.sy"], "conflicting_prototype.err");
+}
+
+#[test]
+fn preprocess() {
+    check_koopa(&[], &["preprocess.sy"], "preprocess.koopa");
+}
+
+#[test]
+fn preprocess_unterminated() {
+    check_error(&[], &["preprocess_unterminated.sy"], "preprocess_unterminated.err");
+}
+
+#[test]
+fn putf() {
+    check_koopa(&[], &["putf.sy"], "putf.koopa");
+    check_riscv(&[], &["putf.sy"], "putf.s");
+}
+
+#[test]
+fn strings() {
+    check_koopa(&["-ext"], &["strings.sy"], "strings.koopa");
+}
+
+#[test]
+fn putf_non_literal() {
+    check_error(&[], &["putf_non_literal.sy"], "putf_non_literal.err");
+}
+
+#[test]
+fn chars() {
+    check_koopa(&[], &["chars.sy"], "chars.koopa");
+}
+
+#[test]
+fn char_bad_escape() {
+    check_error(&[], &["char_bad_escape.sy"], "char_bad_escape.err");
+}
+
+#[test]
+fn goto() {
+    check_koopa(&[], &["goto.sy"], "goto.koopa");
+    check_riscv(&[], &["goto.sy"], "goto.s");
+}
+
+#[test]
+fn goto_undefined_label() {
+    check_error(&[], &["goto_undefined_label.sy"], "goto_undefined_label.err");
+}
+
+#[test]
+fn static_locals() {
+    check_koopa(&[], &["static_locals.sy"], "static_locals.koopa");
+    check_riscv(&[], &["static_locals.sy"], "static_locals.s");
+}
+
+#[test]
+fn static_non_const_init() {
+    check_error(&[], &["static_non_const_init.sy"], "static_non_const_init.err");
+}
+
+#[test]
+fn static_call_init() {
+    check_error(&[], &["static_call_init.sy"], "static_call_init.err");
+}
+
+#[test]
+fn typedef_enum() {
+    check_koopa(&[], &["typedef_enum.sy"], "typedef_enum.koopa");
+}
+
+#[test]
+fn typedef_as_variable() {
+    check_error(&[], &["typedef_as_variable.sy"], "typedef_as_variable.err");
+}
+
+#[test]
+fn structs() {
+    check_koopa(&[], &["structs.sy"], "structs.koopa");
+}
+
+#[test]
+fn struct_unknown_field() {
+    check_error(&[], &["struct_unknown_field.sy"], "struct_unknown_field.err");
+}
+
+#[test]
+fn pointers() {
+    check_koopa(&["-ext"], &["pointers.sy"], "pointers.koopa");
+}
+
+#[test]
+fn pointers_without_ext() {
+    check_error(&[], &["pointers_without_ext.sy"], "pointers_without_ext.err");
+}
+
+#[test]
+fn pointer_comparison() {
+    check_error(&["-ext"], &["pointer_comparison.sy"], "pointer_comparison.err");
+}
+
+#[test]
+fn pointer_difference() {
+    check_error(&["-ext"], &["pointer_difference.sy"], "pointer_difference.err");
+}
+
+#[test]
+fn link() {
+    check_koopa(&[], &["link_main.sy", "link_lib.sy"], "link.koopa");
+    check_riscv(&[], &["link_main.sy", "link_lib.sy"], "link.s");
+}
+
+#[test]
+fn link_duplicate() {
+    check_error(&[], &["link_main.sy", "link_lib.sy", "link_duplicate.sy"], "link_duplicate.err");
+}
+
+#[test]
+fn link_undefined() {
+    check_error(&[], &["link_undefined.sy"], "link_undefined.err");
+}
+
+#[test]
+fn link_unused_extern() {
+    check_koopa(&[], &["link_unused_extern.sy"], "link_unused_extern.koopa");
+}
+
+#[test]
+fn extern_definition() {
+    check_koopa(&[], &["extern_definition.sy"], "extern_definition.koopa");
+}
+
+#[test]
+fn extern_conflicting_definition() {
+    check_error(&[], &["extern_conflicting_definition.sy"], "extern_conflicting_definition.err");
+}
+
+#[test]
+fn scopes() {
+    check_koopa(&[], &["scopes.sy"], "scopes.koopa");
+}
+
+#[test]
+fn duplicate_param() {
+    check_error(&[], &["duplicate_param.sy"], "duplicate_param.err");
+}
+
+#[test]
+fn return_mismatch() {
+    check_error(&[], &["return_mismatch.sy"], "return_mismatch.err");
+}
+
+#[test]
+fn too_few_arguments() {
+    check_error(&[], &["too_few_arguments.sy"], "too_few_arguments.err");
+}
+
+#[test]
+fn too_many_arguments() {
+    check_error(&[], &["too_many_arguments.sy"], "too_many_arguments.err");
+}
+
+#[test]
+fn missing_return() {
+    check_koopa(&[], &["missing_return.sy"], "missing_return.koopa");
+    check_warning(&[], &["missing_return.sy"], "missing_return.warn");
+}
+
+#[test]
+fn missing_return_werror() {
+    check_error(&["-Werror"], &["missing_return.sy"], "missing_return.err");
+}
@@ -0,0 +1,63 @@
+  .text
+  .globl main_exit
+main_exit:
+  addi sp, sp, -16
+.Lmain_exit.entry:
+  li t0, 1
+  sw t0, 12(sp)
+  j .Lmain_exit.exit
+.Lmain_exit.exit:
+  lw t0, 12(sp)
+  sw t0, 8(sp)
+  lw a0, 8(sp)
+  addi sp, sp, 16
+  ret
+  .globl main
+main:
+  addi sp, sp, -48
+  sw ra, 44(sp)
+.Lmain.entry:
+  li t0, 0
+  sw t0, 36(sp)
+  j .Lmain.label_loop
+.Lmain.label_loop:
+  lw t0, 36(sp)
+  sw t0, 32(sp)
+  lw t0, 32(sp)
+  li t1, 1
+  add t0, t0, t1
+  sw t0, 28(sp)
+  lw t0, 28(sp)
+  sw t0, 36(sp)
+  lw t0, 36(sp)
+  sw t0, 24(sp)
+  lw t0, 24(sp)
+  li t1, 10
+  slt t0, t0, t1
+  sw t0, 20(sp)
+  lw t0, 20(sp)
+  bnez t0, .Lmain.then_0
+  j .Lmain.end_0
+.Lmain.then_0:
+  j .Lmain.label_loop
+.Lmain.end_0:
+  j .Lmain.label_done
+.Lmain.label_done:
+  lw t0, 36(sp)
+  sw t0, 16(sp)
+  call main_exit
+  sw a0, 12(sp)
+  lw t0, 16(sp)
+  lw t1, 12(sp)
+  add t0, t0, t1
+  sw t0, 8(sp)
+  lw t0, 8(sp)
+  sw t0, 40(sp)
+  j .Lmain.exit
+.Lmain.exit:
+  lw t0, 40(sp)
+  sw t0, 4(sp)
+  lw a0, 4(sp)
+  lw ra, 44(sp)
+  addi sp, sp, 48
+  ret
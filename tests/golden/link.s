@@ -0,0 +1,100 @@
+  .data
+_sysy_static_count_0:
+  .word 1
+
+  .section .rodata
+_sysy_str_0_1:
+  .word 1918989427
+  .word 2676
+
+  .data
+  .globl total
+total:
+  .word 0
+
+  .data
+  .globl _sysy_str_0
+_sysy_str_0:
+  .word 7
+
+  .data
+_sysy_static_count_0_1:
+  .word 0
+
+  .text
+  .globl main
+main:
+  addi sp, sp, -32
+  sw ra, 28(sp)
+.Lmain.entry:
+  la t0, _sysy_str_0_1
+  addi t0, t0, 0
+  sw t0, 20(sp)
+  lw a0, 20(sp)
+  call putf
+  la t0, _sysy_static_count_0
+  lw t0, 0(t0)
+  sw t0, 16(sp)
+  lw a0, 16(sp)
+  call add
+  sw a0, 12(sp)
+  la t0, total
+  lw t0, 0(t0)
+  sw t0, 8(sp)
+  lw t0, 8(sp)
+  sw t0, 24(sp)
+  j .Lmain.exit
+.Lmain.exit:
+  lw t0, 24(sp)
+  sw t0, 4(sp)
+  lw a0, 4(sp)
+  lw ra, 28(sp)
+  addi sp, sp, 32
+  ret
+  .globl add
+add:
+  addi sp, sp, -48
+.Ladd.entry:
+  mv t0, a0
+  sw t0, 40(sp)
+  la t0, _sysy_static_count_0_1
+  lw t0, 0(t0)
+  sw t0, 36(sp)
+  lw t0, 36(sp)
+  li t1, 1
+  add t0, t0, t1
+  sw t0, 32(sp)
+  lw t0, 32(sp)
+  la t1, _sysy_static_count_0_1
+  sw t0, 0(t1)
+  la t0, total
+  lw t0, 0(t0)
+  sw t0, 28(sp)
+  lw t0, 40(sp)
+  sw t0, 24(sp)
+  lw t0, 28(sp)
+  lw t1, 24(sp)
+  add t0, t0, t1
+  sw t0, 20(sp)
+  la t0, _sysy_str_0
+  lw t0, 0(t0)
+  sw t0, 16(sp)
+  lw t0, 20(sp)
+  lw t1, 16(sp)
+  add t0, t0, t1
+  sw t0, 12(sp)
+  lw t0, 12(sp)
+  la t1, total
+  sw t0, 0(t1)
+  la t0, total
+  lw t0, 0(t0)
+  sw t0, 8(sp)
+  lw t0, 8(sp)
+  sw t0, 44(sp)
+  j .Ladd.exit
+.Ladd.exit:
+  lw t0, 44(sp)
+  sw t0, 4(sp)
+  lw a0, 4(sp)
+  addi sp, sp, 48
+  ret
@@ -0,0 +1,150 @@
+  .section .rodata
+_sysy_str_0:
+  .word 1819043176
+  .word 2671
+
+  .section .rodata
+_sysy_str_1:
+  .word 723543077
+  .word 543434016
+  .word 1680154685
+  .word 10
+
+  .section .rodata
+_sysy_str_2:
+  .word 1680172069
+  .word 1680172069
+  .word 1680172069
+  .word 1680172069
+  .word 10
+
+  .text
+  .globl main
+main:
+  addi sp, sp, -128
+  sw ra, 124(sp)
+.Lmain.entry:
+  la t0, _sysy_str_0
+  addi t0, t0, 0
+  sw t0, 116(sp)
+  lw a0, 116(sp)
+  call putf
+  la t0, _sysy_str_1
+  addi t0, t0, 0
+  sw t0, 112(sp)
+  li t0, 1
+  li t1, 2
+  add t0, t0, t1
+  sw t0, 108(sp)
+  addi t0, sp, 96
+  addi t0, t0, 0
+  sw t0, 92(sp)
+  li t0, 1
+  lw t1, 92(sp)
+  sw t0, 0(t1)
+  addi t0, sp, 96
+  addi t0, t0, 4
+  sw t0, 88(sp)
+  li t0, 2
+  lw t1, 88(sp)
+  sw t0, 0(t1)
+  addi t0, sp, 96
+  addi t0, t0, 8
+  sw t0, 84(sp)
+  lw t0, 108(sp)
+  lw t1, 84(sp)
+  sw t0, 0(t1)
+  addi t0, sp, 96
+  addi t0, t0, 0
+  sw t0, 80(sp)
+  lw a0, 112(sp)
+  lw a1, 80(sp)
+  lw a1, 0(a1)
+  lw a2, 80(sp)
+  lw a2, 4(a2)
+  lw a3, 80(sp)
+  lw a3, 8(a3)
+  call putf
+  la t0, _sysy_str_2
+  addi t0, t0, 0
+  sw t0, 76(sp)
+  addi t0, sp, 44
+  addi t0, t0, 0
+  sw t0, 40(sp)
+  li t0, 1
+  lw t1, 40(sp)
+  sw t0, 0(t1)
+  addi t0, sp, 44
+  addi t0, t0, 4
+  sw t0, 36(sp)
+  li t0, 2
+  lw t1, 36(sp)
+  sw t0, 0(t1)
+  addi t0, sp, 44
+  addi t0, t0, 8
+  sw t0, 32(sp)
+  li t0, 3
+  lw t1, 32(sp)
+  sw t0, 0(t1)
+  addi t0, sp, 44
+  addi t0, t0, 12
+  sw t0, 28(sp)
+  li t0, 4
+  lw t1, 28(sp)
+  sw t0, 0(t1)
+  addi t0, sp, 44
+  addi t0, t0, 16
+  sw t0, 24(sp)
+  li t0, 5
+  lw t1, 24(sp)
+  sw t0, 0(t1)
+  addi t0, sp, 44
+  addi t0, t0, 20
+  sw t0, 20(sp)
+  li t0, 6
+  lw t1, 20(sp)
+  sw t0, 0(t1)
+  addi t0, sp, 44
+  addi t0, t0, 24
+  sw t0, 16(sp)
+  li t0, 7
+  lw t1, 16(sp)
+  sw t0, 0(t1)
+  addi t0, sp, 44
+  addi t0, t0, 28
+  sw t0, 12(sp)
+  li t0, 8
+  lw t1, 12(sp)
+  sw t0, 0(t1)
+  addi t0, sp, 44
+  addi t0, t0, 0
+  sw t0, 8(sp)
+  lw a0, 76(sp)
+  lw a1, 8(sp)
+  lw a1, 0(a1)
+  lw a2, 8(sp)
+  lw a2, 4(a2)
+  lw a3, 8(sp)
+  lw a3, 8(a3)
+  lw a4, 8(sp)
+  lw a4, 12(a4)
+  lw a5, 8(sp)
+  lw a5, 16(a5)
+  lw a6, 8(sp)
+  lw a6, 20(a6)
+  lw a7, 8(sp)
+  lw a7, 24(a7)
+  lw t0, 8(sp)
+  lw t0, 28(t0)
+  sw t0, 0(sp)
+  call putf
+  li t0, 0
+  sw t0, 120(sp)
+  j .Lmain.exit
+.Lmain.exit:
+  lw t0, 120(sp)
+  sw t0, 4(sp)
+  lw a0, 4(sp)
+  lw ra, 124(sp)
+  addi sp, sp, 128
+  ret
@@ -0,0 +1,88 @@
+  .data
+  .globl counter
+counter:
+  .word 100
+
+  .data
+_sysy_static_counter_0:
+  .word 0
+
+  .data
+_sysy_static_counter_1:
+  .zero 4
+
+  .text
+  .globl next
+next:
+  addi sp, sp, -32
+.Lnext.entry:
+  la t0, _sysy_static_counter_0
+  lw t0, 0(t0)
+  sw t0, 24(sp)
+  lw t0, 24(sp)
+  li t1, 1
+  add t0, t0, t1
+  sw t0, 20(sp)
+  lw t0, 20(sp)
+  la t1, _sysy_static_counter_0
+  sw t0, 0(t1)
+  la t0, _sysy_static_counter_0
+  lw t0, 0(t0)
+  sw t0, 16(sp)
+  lw t0, 16(sp)
+  sw t0, 28(sp)
+  j .Lnext.exit
+.Lnext.exit:
+  lw t0, 28(sp)
+  sw t0, 12(sp)
+  lw a0, 12(sp)
+  addi sp, sp, 32
+  ret
+  .globl other
+other:
+  addi sp, sp, -16
+.Lother.entry:
+  la t0, _sysy_static_counter_1
+  lw t0, 0(t0)
+  sw t0, 8(sp)
+  lw t0, 8(sp)
+  sw t0, 12(sp)
+  j .Lother.exit
+.Lother.exit:
+  lw t0, 12(sp)
+  sw t0, 4(sp)
+  lw a0, 4(sp)
+  addi sp, sp, 16
+  ret
+  .globl main
+main:
+  addi sp, sp, -48
+  sw ra, 44(sp)
+.Lmain.entry:
+  call next
+  sw a0, 36(sp)
+  call next
+  sw a0, 32(sp)
+  call other
+  sw a0, 28(sp)
+  lw t0, 32(sp)
+  lw t1, 28(sp)
+  add t0, t0, t1
+  sw t0, 24(sp)
+  la t0, counter
+  lw t0, 0(t0)
+  sw t0, 20(sp)
+  lw t0, 24(sp)
+  lw t1, 20(sp)
+  add t0, t0, t1
+  sw t0, 16(sp)
+  lw t0, 16(sp)
+  sw t0, 40(sp)
+  j .Lmain.exit
+.Lmain.exit:
+  lw t0, 40(sp)
+  sw t0, 12(sp)
+  lw a0, 12(sp)
+  lw ra, 44(sp)
+  addi sp, sp, 48
+  ret
@@ -0,0 +1,80 @@
+  .text
+  .globl main
+main:
+  addi sp, sp, -64
+  sw ra, 60(sp)
+.Lmain.entry:
+  call getint
+  sw a0, 48(sp)
+  lw t0, 48(sp)
+  sw t0, 52(sp)
+  li t0, 0
+  sw t0, 44(sp)
+  lw t0, 52(sp)
+  sw t0, 40(sp)
+  j .Lmain.switch_table_0
+.Lmain.switch_table_0:
+  lw t0, 40(sp)
+  li t1, 1
+  sub t0, t0, t1
+  li t1, 6
+  bgeu t0, t1, .Lmain.default_0
+  la t1, .Lmain.switch_table_0.labels
+  slli t0, t0, 2
+  add t1, t1, t0
+  lw t1, 0(t1)
+  jr t1
+  .section .rodata
+.Lmain.switch_table_0.labels:
+  .word .Lmain.case_0_0
+  .word .Lmain.case_0_1
+  .word .Lmain.case_0_2
+  .word .Lmain.case_0_3
+  .word .Lmain.default_0
+  .word .Lmain.case_0_4
+  .text
+.Lmain.case_0_0:
+  li t0, 10
+  sw t0, 44(sp)
+  j .Lmain.case_0_1
+.Lmain.case_0_1:
+  lw t0, 44(sp)
+  sw t0, 16(sp)
+  lw t0, 16(sp)
+  li t1, 20
+  add t0, t0, t1
+  sw t0, 12(sp)
+  lw t0, 12(sp)
+  sw t0, 44(sp)
+  j .Lmain.switch_end_0
+.Lmain.case_0_2:
+  j .Lmain.case_0_3
+.Lmain.case_0_3:
+  li t0, 30
+  sw t0, 44(sp)
+  j .Lmain.switch_end_0
+.Lmain.case_0_4:
+  li t0, 40
+  sw t0, 44(sp)
+  j .Lmain.switch_end_0
+.Lmain.default_0:
+  li t0, 0
+  li t1, 1
+  sub t0, t0, t1
+  sw t0, 8(sp)
+  lw t0, 8(sp)
+  sw t0, 44(sp)
+  j .Lmain.switch_end_0
+.Lmain.switch_end_0:
+  lw t0, 44(sp)
+  sw t0, 4(sp)
+  lw t0, 4(sp)
+  sw t0, 56(sp)
+  j .Lmain.exit
+.Lmain.exit:
+  lw t0, 56(sp)
+  sw t0, 0(sp)
+  lw a0, 0(sp)
+  lw ra, 60(sp)
+  addi sp, sp, 64
+  ret